@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 
 use papa::prelude::*;
-use papa::utils::kwd;
+use papa::utils::{kwd, number, string_literal, Number, StringConfig};
 
 pub fn main() {
     let s = "{}";
@@ -16,11 +16,12 @@ pub fn main() {
 }
 
 #[derive(Debug)]
+#[allow(dead_code)]
 enum Json {
     Object(HashMap<String, Json>),
     Array(Vec<Json>),
     String(String),
-    Integer(u64),
+    Integer(i64),
     Float(f64),
     Bool(bool),
     Null,
@@ -39,20 +40,23 @@ fn parse_value<'a>() -> impl Parser<&'a str, Json, ()> {
     })
 }
 
-fn parse_object<'a>(expr: impl Parser<&'a str, Json, ()>) -> impl Parser<&'a str, Json, ()> {
+fn parse_object<'a>(_expr: impl Parser<&'a str, Json, ()>) -> impl Parser<&'a str, Json, ()> {
     dummy_parser()
 }
 
-fn parse_array<'a>(expr: impl Parser<&'a str, Json, ()>) -> impl Parser<&'a str, Json, ()> {
+fn parse_array<'a>(_expr: impl Parser<&'a str, Json, ()>) -> impl Parser<&'a str, Json, ()> {
     dummy_parser()
 }
 
 fn parse_string<'a>() -> impl Parser<&'a str, Json, ()> {
-    dummy_parser()
+    string_literal(StringConfig::json()).map(|s| Json::String(s.into_owned()))
 }
 
 fn parse_number<'a>() -> impl Parser<&'a str, Json, ()> {
-    dummy_parser()
+    number().map(|number| match number {
+        Number::Int(i) => Json::Integer(i),
+        Number::Float(f) => Json::Float(f),
+    })
 }
 
 fn parse_bool<'a>() -> impl Parser<&'a str, Json, ()> {
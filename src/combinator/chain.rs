@@ -46,13 +46,13 @@ macro_rules! impl_chain {
             $($parser: Parser<In, [<$parser Out>], Error>,)*
         {
             fn parse_chain(&self, context: &mut Context<In, Error>) -> ParseResult<($([<$parser Out>],)*), Error> {
-                let start = context.location();
+                let start = context.checkpoint();
 
                 $(
                     let [<$parser:lower _out>] = match self.$n.parse(context) {
                          Ok(output) => output,
                          Err(err) => {
-                             context.set_location(start);
+                             context.rewind(start);
                              return Err(err);
                          }
                     };
@@ -5,6 +5,11 @@ use crate::context::Context;
 use crate::prelude::*;
 
 /// A parser that tries to parse one of a tuple of parsers.
+///
+/// The parsers are tried in order, and the output of the first one that
+/// matches is used. A parser that fails with an error is treated like one
+/// that doesn't match, unless the error comes from a [`Parser::cut`] parser,
+/// which makes the whole choice fail with it.
 pub fn choice<In: Slice, Out, Error, P: ChoiceParsers<In, Out, Error>>(
     parsers: P,
 ) -> Choice<In, Out, Error, P> {
@@ -46,12 +51,13 @@ macro_rules! impl_choice {
         {
             fn parse_choice(&self, context: &mut Context<In, Error>) -> ParseResult<Out, Error> {
                 $(
-                    let start = context.location();
-                    let result = self.$n.parse(context);
-                    if result.is_ok() {
+                    context.set_cut(false);
+                    let result = context.trace(concat!("choice ", stringify!($n)), |context| {
+                        context.attempt(|context| self.$n.parse(context))
+                    });
+                    if result.is_ok() || matches!(result, Err(Some(_))) && context.is_cut() {
                         return result;
                     }
-                    context.set_location(start);
                 )*
 
                 Err(None)
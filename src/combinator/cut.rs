@@ -0,0 +1,23 @@
+use std::marker::PhantomData;
+
+use crate::context::slice::Slice;
+use crate::context::Context;
+use crate::prelude::*;
+
+#[derive_where::derive_where(Debug, Clone; P)]
+pub struct Cut<In: Slice, Out, Error, P: Parser<In, Out, Error>> {
+    pub(crate) parser: P,
+    pub(crate) _phantom: PhantomData<*const (In, Out, Error)>,
+}
+
+impl<In: Slice, Out, Error, P: Parser<In, Out, Error>> Parser<In, Out, Error>
+    for Cut<In, Out, Error, P>
+{
+    fn parse(&self, context: &mut Context<In, Error>) -> ParseResult<Out, Error> {
+        let result = self.parser.parse(context);
+        if matches!(result, Err(Some(_))) {
+            context.set_cut(true);
+        }
+        result
+    }
+}
@@ -20,7 +20,6 @@ where
     P: Parser<In, Out, Error>,
 {
     fn parse(&self, context: &mut Context<In, Error>) -> ParseResult<(), Error> {
-        let _ = self.parser.parse(context);
-        Ok(())
+        self.parser.parse(context).map(|_| ())
     }
 }
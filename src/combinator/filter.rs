@@ -23,12 +23,14 @@ where
     F: Fn(&Out) -> bool,
 {
     fn parse(&self, context: &mut Context<In, Error>) -> ParseResult<Out, Error> {
-        self.parser.parse(context).and_then(|output| {
-            if (self.filter)(&output) {
-                Ok(output)
-            } else {
-                Err(None)
-            }
+        context.attempt(|context| {
+            self.parser.parse(context).and_then(|output| {
+                if (self.filter)(&output) {
+                    Ok(output)
+                } else {
+                    Err(None)
+                }
+            })
         })
     }
 }
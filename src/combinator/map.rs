@@ -1,6 +1,7 @@
 use std::marker::PhantomData;
 
 use crate::context::slice::Slice;
+use crate::context::state::State;
 use crate::context::Context;
 use crate::prelude::*;
 
@@ -9,7 +10,7 @@ pub struct Map<In, OA, OB, Error, P, F>
 where
     In: Slice,
     P: Parser<In, OA, Error>,
-    F: Fn(OA) -> OB + Copy,
+    F: Fn(OA) -> OB,
 {
     pub(crate) parser: P,
     pub(crate) map: F,
@@ -20,9 +21,67 @@ impl<In, OA, OB, Error, P, F> Parser<In, OB, Error> for Map<In, OA, OB, Error, P
 where
     In: Slice,
     P: Parser<In, OA, Error>,
-    F: Fn(OA) -> OB + Copy,
+    F: Fn(OA) -> OB,
 {
     fn parse(&self, context: &mut Context<In, Error>) -> ParseResult<OB, Error> {
-        self.parser.parse(context).map(self.map)
+        self.parser.parse(context).map(&self.map)
+    }
+}
+
+#[derive_where::derive_where(Debug, Clone; P, F)]
+pub struct MapWith<In, OA, OB, Error, P, F>
+where
+    In: Slice,
+    P: Parser<In, OA, Error>,
+    F: Fn(OA, &mut MapExtra<In, Error>) -> OB,
+{
+    pub(crate) parser: P,
+    pub(crate) map: F,
+    pub(crate) _phantom: PhantomData<*const (In, OA, OB, Error)>,
+}
+
+impl<In, OA, OB, Error, P, F> Parser<In, OB, Error> for MapWith<In, OA, OB, Error, P, F>
+where
+    In: Slice,
+    P: Parser<In, OA, Error>,
+    F: Fn(OA, &mut MapExtra<In, Error>) -> OB,
+{
+    fn parse(&self, context: &mut Context<In, Error>) -> ParseResult<OB, Error> {
+        let start = context.location();
+        let output = self.parser.parse(context)?;
+
+        let mut extra = MapExtra { start, context };
+        Ok((self.map)(output, &mut extra))
+    }
+}
+
+/// Extra information about a match, passed to [`Parser::map_with`].
+pub struct MapExtra<'a, In: Slice, Error> {
+    start: usize,
+    context: &'a mut Context<In, Error>,
+}
+
+impl<In: Slice, Error> MapExtra<'_, In, Error> {
+    /// Get the span of the matched input.
+    pub fn span(&self) -> Span {
+        self.context.span_from(self.start)
+    }
+
+    /// Get some user-defined state from the context.
+    pub fn state<S: State>(&self) -> Option<&S> {
+        self.context.state()
+    }
+
+    /// Get some user-defined state from the context, mutably.
+    pub fn state_mut<S: State>(&mut self) -> Option<&mut S> {
+        self.context.state_mut()
+    }
+
+    /// Get the matched input.
+    pub fn slice(&self) -> In {
+        self.context
+            .slice_all()
+            .slice(self.start, self.context.location())
+            .expect("couldn't get slice")
     }
 }
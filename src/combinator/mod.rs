@@ -1,8 +1,10 @@
 pub mod chain;
 pub mod choice;
+pub mod cut;
 pub mod drop;
 pub mod filter;
 pub mod map;
 pub mod repeat;
 pub mod spanned;
 pub mod to_slice;
+pub mod traced;
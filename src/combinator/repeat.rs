@@ -2,31 +2,123 @@ use std::marker::PhantomData;
 
 use crate::context::slice::Slice;
 use crate::context::Context;
+use crate::error::{ErrorKind, ParseError};
 use crate::prelude::*;
 
+/// A parser that matches another parser repeatedly.
+///
+/// The repetition ends when the parser fails. An error from a
+/// [`Parser::cut`] parser makes the whole repetition fail with it instead.
+///
+/// An iteration that succeeds without consuming any input would repeat
+/// forever, so it always ends the repetition. What else happens depends on
+/// the configuration:
+///
+/// - by default, this panics in debug builds, and stops silently in release
+///   builds.
+/// - with [`Repeat::allow_zero_width`], it stops silently.
+/// - with [`Repeat::zero_width_error`], it reports an error.
+///
+/// When stopping, the output of the zero-width iteration is kept.
+///
+/// If a maximum is set with [`Repeat::max`], the repetition can't loop
+/// forever, so by default zero-width iterations are allowed and count
+/// towards the maximum.
 #[derive_where::derive_where(Debug, Clone; P)]
 pub struct Repeat<In: Slice, Out, Error, P: Parser<In, Out, Error>, Collection: FromIterator<Out>> {
     pub(crate) parser: P,
     pub(crate) min: usize,
     pub(crate) max: Option<usize>,
+    pub(crate) label: Option<&'static str>,
+    pub(crate) zero_width: ZeroWidth,
+    pub(crate) too_few_error: bool,
+    pub(crate) make_error: Option<fn(ParseError) -> Error>,
     pub(crate) _phantom: PhantomData<*const (In, Out, Error, Collection)>,
 }
 
+/// What [`Repeat`] does when an iteration doesn't consume any input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ZeroWidth {
+    Panic,
+    Stop,
+    Error,
+}
+
 impl<In, Out, Error, P, Collection> Repeat<In, Out, Error, P, Collection>
 where
     In: Slice,
     P: Parser<In, Out, Error>,
     Collection: FromIterator<Out>,
 {
+    pub(crate) fn new(parser: P) -> Self {
+        Self {
+            parser,
+            min: 0,
+            max: None,
+            label: None,
+            zero_width: ZeroWidth::Panic,
+            too_few_error: false,
+            make_error: None,
+            _phantom: PhantomData,
+        }
+    }
+
     /// Set the minimum number of times to match.
+    ///
+    /// This undoes [`Repeat::exactly`], so too few matches is no longer an
+    /// error.
     pub fn min(mut self, min: usize) -> Self {
         self.min = min;
+        self.too_few_error = false;
         self
     }
 
     /// Set the maximum number of times to match.
+    ///
+    /// This undoes [`Repeat::exactly`], so too few matches is no longer an
+    /// error.
     pub fn max(mut self, max: usize) -> Self {
         self.max = Some(max);
+        self.too_few_error = false;
+        self
+    }
+
+    /// Match exactly `n` times.
+    ///
+    /// Shorthand for `.min(n).max(n)`, except that finding fewer than `n`
+    /// matches is reported as an error.
+    pub fn exactly(mut self, n: usize) -> Self
+    where
+        Error: From<ParseError>,
+    {
+        self.min = n;
+        self.max = Some(n);
+        self.too_few_error = true;
+        self.make_error = Some(Error::from);
+        self
+    }
+
+    /// Set the name of the repeated parser, used in panic and error messages.
+    ///
+    /// Defaults to the type name of the parser.
+    pub fn label(mut self, label: &'static str) -> Self {
+        self.label = Some(label);
+        self
+    }
+
+    /// Stop silently when an iteration doesn't consume any input.
+    pub fn allow_zero_width(mut self) -> Self {
+        self.zero_width = ZeroWidth::Stop;
+        self
+    }
+
+    /// Report an error when an iteration doesn't consume any input.
+    pub fn zero_width_error(mut self) -> Self
+    where
+        Error: From<ParseError>,
+    {
+        self.zero_width = ZeroWidth::Error;
+        self.make_error = Some(Error::from);
         self
     }
 
@@ -39,9 +131,17 @@ where
             parser: self.parser,
             min: self.min,
             max: self.max,
+            label: self.label,
+            zero_width: self.zero_width,
+            too_few_error: self.too_few_error,
+            make_error: self.make_error,
             _phantom: PhantomData,
         }
     }
+
+    fn label_or_type_name(&self) -> &'static str {
+        self.label.unwrap_or_else(std::any::type_name::<P>)
+    }
 }
 
 impl<In, Out, Error, P, Collection> Parser<In, Collection, Error>
@@ -53,11 +153,50 @@ where
 {
     fn parse(&self, context: &mut Context<In, Error>) -> ParseResult<Collection, Error> {
         let start = context.location();
+        let checkpoint = context.checkpoint();
         let mut num_matches = 0;
+        let mut zero_width_error = None;
+        let mut cut_error = None;
+        let mut done = false;
 
         let parse_iter = std::iter::from_fn(|| {
-            let output = self.parser.parse(context).ok()?;
+            if done {
+                return None;
+            }
+
+            let iter_start = context.location();
+            context.set_cut(false);
+            let output = match context.attempt(|context| self.parser.parse(context)) {
+                Ok(output) => output,
+                Err(Some(error)) if context.is_cut() => {
+                    cut_error = Some(error);
+                    return None;
+                }
+                Err(_) => return None,
+            };
             num_matches += 1;
+
+            let bounded = self.max.is_some() && self.zero_width == ZeroWidth::Panic;
+            if context.location() == iter_start && !bounded {
+                done = true;
+
+                match self.zero_width {
+                    ZeroWidth::Panic if cfg!(debug_assertions) => panic!(
+                        "repeated parser `{}` matched empty input, which would loop forever",
+                        self.label_or_type_name()
+                    ),
+                    ZeroWidth::Panic | ZeroWidth::Stop => {}
+                    ZeroWidth::Error => {
+                        zero_width_error = Some(ParseError::new(
+                            ErrorKind::ZeroWidthRepeat {
+                                label: self.label_or_type_name(),
+                            },
+                            context.span_from(iter_start),
+                        ));
+                    }
+                }
+            }
+
             Some(output)
         });
 
@@ -67,15 +206,93 @@ where
             Collection::from_iter(parse_iter)
         };
 
+        if let Some(error) = cut_error {
+            context.rewind(checkpoint);
+            return Err(Some(error));
+        }
+
+        if let (Some(error), Some(make_error)) = (zero_width_error, self.make_error) {
+            context.rewind(checkpoint);
+            return Err(Some(make_error(error)));
+        }
+
         if num_matches < self.min {
-            context.set_location(start);
-            Err(None)
+            let error = match self.make_error {
+                Some(make_error) if self.too_few_error => Some(make_error(ParseError::new(
+                    ErrorKind::TooFewRepeats {
+                        expected: self.min,
+                        found: num_matches,
+                    },
+                    context.span_from(start),
+                ))),
+                _ => None,
+            };
+            context.rewind(checkpoint);
+            Err(error)
         } else {
             Ok(collection)
         }
     }
 }
 
+/// A parser that matches another parser exactly `N` times, outputting an
+/// array.
+///
+/// Finding fewer than `N` matches is reported as an error, as with
+/// [`Repeat::exactly`].
+#[derive_where::derive_where(Debug, Clone; P)]
+pub struct RepeatExactly<In: Slice, Out, Error, P: Parser<In, Out, Error>, const N: usize> {
+    pub(crate) parser: P,
+    pub(crate) _phantom: PhantomData<*const (In, Out, Error)>,
+}
+
+impl<In, Out, Error, P, const N: usize> Parser<In, [Out; N], Error>
+    for RepeatExactly<In, Out, Error, P, N>
+where
+    In: Slice,
+    P: Parser<In, Out, Error>,
+    Error: From<ParseError>,
+{
+    fn parse(&self, context: &mut Context<In, Error>) -> ParseResult<[Out; N], Error> {
+        let start = context.checkpoint();
+        let start_location = context.location();
+        let mut num_matches = 0;
+        let mut error = None;
+
+        let outputs: [Option<Out>; N] = std::array::from_fn(|_| {
+            if error.is_some() {
+                return None;
+            }
+
+            match context.attempt(|context| self.parser.parse(context)) {
+                Ok(output) => {
+                    num_matches += 1;
+                    Some(output)
+                }
+                Err(err) => {
+                    error = Some(err);
+                    None
+                }
+            }
+        });
+
+        if let Some(error) = error {
+            let error = error.or_else(|| {
+                let kind = ErrorKind::TooFewRepeats {
+                    expected: N,
+                    found: num_matches,
+                };
+                let span = context.span_from(start_location);
+                Some(ParseError::new(kind, span).into())
+            });
+            context.rewind(start);
+            return Err(error);
+        }
+
+        Ok(outputs.map(|output| output.expect("all iterations matched")))
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct NoRepeatOutput;
 
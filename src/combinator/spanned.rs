@@ -17,9 +17,6 @@ impl<In: Slice, Out, Error, P: Parser<In, Out, Error>> Parser<In, (Span, Out), E
         let start = context.location();
         let output = self.parser.parse(context);
 
-        output.map(|output| {
-            let span = Span::new(start, context.location());
-            (span, output)
-        })
+        output.map(|output| (context.span_from(start), output))
     }
 }
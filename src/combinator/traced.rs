@@ -0,0 +1,20 @@
+use std::marker::PhantomData;
+
+use crate::context::slice::Slice;
+use crate::context::Context;
+use crate::prelude::*;
+
+#[derive_where::derive_where(Debug, Clone; P)]
+pub struct Traced<In: Slice, Out, Error, P: Parser<In, Out, Error>> {
+    pub(crate) parser: P,
+    pub(crate) name: &'static str,
+    pub(crate) _phantom: PhantomData<*const (In, Out, Error)>,
+}
+
+impl<In: Slice, Out, Error, P: Parser<In, Out, Error>> Parser<In, Out, Error>
+    for Traced<In, Out, Error, P>
+{
+    fn parse(&self, context: &mut Context<In, Error>) -> ParseResult<Out, Error> {
+        context.trace(self.name, |context| self.parser.parse(context))
+    }
+}
@@ -0,0 +1,252 @@
+//! Text input with spans in characters or UTF-16 code units, and line and
+//! column numbers.
+//!
+//! Parse a [`Located`] from [`LineIndex::slice`]. Locations in the
+//! [`Context`](super::Context) are still byte offsets, but spans (such as
+//! those from [`Parser::spanned`] and in errors) are in the [`Unit`] of the
+//! index. The text is scanned for line starts lazily, only as far as the
+//! furthest span or position asked for, and never more than once, so
+//! there's no separate pass over the text up front.
+//!
+//! [`Parser::spanned`]: crate::parser::Parser::spanned
+
+use std::cell::RefCell;
+
+use super::slice::Slice;
+use super::span::Span;
+
+/// The unit that offsets are counted in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Unit {
+    #[default]
+    Byte,
+    Char,
+    /// UTF-16 code units, as used by JavaScript and the Language Server
+    /// Protocol.
+    Utf16,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+struct Offsets {
+    byte: usize,
+    char: usize,
+    utf16: usize,
+}
+
+impl Offsets {
+    fn get(&self, unit: Unit) -> usize {
+        match unit {
+            Unit::Byte => self.byte,
+            Unit::Char => self.char,
+            Unit::Utf16 => self.utf16,
+        }
+    }
+
+    fn advance(&mut self, c: char) {
+        self.byte += c.len_utf8();
+        self.char += 1;
+        self.utf16 += c.len_utf16();
+    }
+}
+
+/// A position in some text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Position {
+    offset: Offsets,
+    line: usize,
+    line_start: Offsets,
+}
+
+impl Position {
+    /// The offset from the start of the text.
+    pub fn offset(&self, unit: Unit) -> usize {
+        self.offset.get(unit)
+    }
+
+    /// The line number, starting from 0.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// The offset from the start of the line.
+    pub fn column(&self, unit: Unit) -> usize {
+        self.offset.get(unit) - self.line_start.get(unit)
+    }
+}
+
+/// Some text, with an index of its lines that is built as spans and
+/// positions are asked for.
+#[derive(Debug)]
+pub struct LineIndex<'a> {
+    text: &'a str,
+    unit: Unit,
+    lines: RefCell<Lines>,
+}
+
+#[derive(Debug)]
+struct Lines {
+    starts: Vec<Offsets>,
+    /// Offsets at least every [`MARK_INTERVAL`] bytes, so that positions in
+    /// long lines can be found without scanning from the start of the line.
+    marks: Vec<Offsets>,
+    /// How far the text has been scanned for line starts.
+    scanned: Offsets,
+}
+
+const MARK_INTERVAL: usize = 256;
+
+impl<'a> LineIndex<'a> {
+    /// Index `text`, with spans in `unit`.
+    pub fn new(text: &'a str, unit: Unit) -> Self {
+        Self {
+            text,
+            unit,
+            lines: RefCell::new(Lines {
+                starts: vec![Offsets::default()],
+                marks: vec![Offsets::default()],
+                scanned: Offsets::default(),
+            }),
+        }
+    }
+
+    pub fn text(&self) -> &'a str {
+        self.text
+    }
+
+    pub fn unit(&self) -> Unit {
+        self.unit
+    }
+
+    /// Get the text as parser input.
+    pub fn slice(&self) -> Located<'_> {
+        Located {
+            index: self,
+            start: 0,
+            end: self.text.len(),
+        }
+    }
+
+    /// Get the position at an offset in the index's unit, such as the start
+    /// or end of a span.
+    ///
+    /// The offset is clamped to the end of the text, and an offset in the
+    /// middle of a character is moved back to its start.
+    pub fn position(&self, offset: usize) -> Position {
+        self.position_in(self.unit, offset)
+    }
+
+    /// Convert a span in bytes to the index's unit.
+    fn span(&self, start: usize, end: usize) -> Span {
+        match self.unit {
+            Unit::Byte => Span::new(start, end),
+            unit => Span::new(
+                self.position_in(Unit::Byte, start).offset(unit),
+                self.position_in(Unit::Byte, end).offset(unit),
+            ),
+        }
+    }
+
+    fn position_in(&self, unit: Unit, offset: usize) -> Position {
+        let mut lines = self.lines.borrow_mut();
+
+        // Find the line starts up to the offset.
+        while lines.scanned.get(unit) < offset {
+            let Some(c) = self.text[lines.scanned.byte..].chars().next() else {
+                break;
+            };
+            lines.scanned.advance(c);
+            let scanned = lines.scanned;
+            if c == '\n' {
+                lines.starts.push(scanned);
+            }
+            if lines
+                .marks
+                .last()
+                .is_some_and(|mark| scanned.byte - mark.byte >= MARK_INTERVAL)
+            {
+                lines.marks.push(scanned);
+            }
+        }
+
+        let line = lines
+            .starts
+            .partition_point(|start| start.get(unit) <= offset)
+            - 1;
+        let line_start = lines.starts[line];
+
+        // Scan from the start of the line, or from a later mark.
+        let mark = lines.marks[lines.marks.partition_point(|mark| mark.get(unit) <= offset) - 1];
+        let mut position = if mark.byte > line_start.byte {
+            mark
+        } else {
+            line_start
+        };
+        for c in self.text[position.byte..].chars() {
+            let mut next = position;
+            next.advance(c);
+            if next.get(unit) > offset {
+                break;
+            }
+            position = next;
+        }
+
+        Position {
+            offset: position,
+            line,
+            line_start,
+        }
+    }
+}
+
+/// Text input from a [`LineIndex`].
+#[derive(Debug, Clone, Copy)]
+pub struct Located<'a> {
+    index: &'a LineIndex<'a>,
+    start: usize,
+    end: usize,
+}
+
+impl<'a> Located<'a> {
+    pub fn as_str(&self) -> &'a str {
+        &self.index.text[self.start..self.end]
+    }
+
+    /// Get the span of this text, in the index's unit.
+    pub fn span(&self) -> Span {
+        self.index.span(self.start, self.end)
+    }
+}
+
+impl Slice for Located<'_> {
+    type Token = char;
+
+    fn next(&self) -> Option<(Self::Token, Self)> {
+        let c = self.as_str().chars().next()?;
+        let rest = Self {
+            start: self.start + c.len_utf8(),
+            ..*self
+        };
+        Some((c, rest))
+    }
+
+    fn slice(&self, start: usize, end: usize) -> Option<Self> {
+        self.as_str().get(start..end)?;
+        Some(Self {
+            index: self.index,
+            start: self.start + start,
+            end: self.start + end,
+        })
+    }
+
+    fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    fn prefix_len(&self, pred: impl FnMut(Self::Token) -> bool) -> usize {
+        self.as_str().prefix_len(pred)
+    }
+
+    fn source_span(&self, start: usize, end: usize) -> Span {
+        self.index.span(self.start + start, self.start + end)
+    }
+}
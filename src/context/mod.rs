@@ -1,14 +1,67 @@
+pub mod located;
+#[cfg(feature = "ropey")]
+pub mod rope;
+pub mod shared;
 pub mod slice;
 pub mod span;
+pub mod state;
+pub mod stream;
+pub mod tokens;
 
 use self::slice::Slice;
+use self::span::Span;
+use self::state::{State, StateCheckpoint, StateMap};
+use crate::error::{ErrorKind, ParseError};
 use crate::prelude::ParseResult;
+#[cfg(feature = "trace")]
+use crate::trace::{Outcome, Trace, Tracer};
 
 pub struct Context<In: Slice, Error> {
     slice_all: In,
     slice_current: In,
 
     errors: Vec<Error>,
+    states: StateMap,
+
+    depth: usize,
+    max_depth: Option<usize>,
+    fuel: Option<usize>,
+    max_errors: Option<usize>,
+    make_error: Option<fn(ParseError) -> Error>,
+    exhausted: bool,
+    /// The number of errors that can't be discarded by rewinding, because
+    /// they were reported before the parse was cut short.
+    kept_errors: usize,
+    /// Whether the error being returned came from a [`Parser::cut`] parser.
+    ///
+    /// [`Parser::cut`]: crate::parser::Parser::cut
+    cut: bool,
+
+    #[cfg(feature = "trace")]
+    tracer: Option<Tracer<In>>,
+}
+
+/// Limits on the resources used by a parse, for parsing untrusted input.
+///
+/// See [`Context::with_limits`] and [`Parser::parse_with_limits`].
+///
+/// [`Parser::parse_with_limits`]: crate::parser::Parser::parse_with_limits
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Limits {
+    /// See [`Context::with_fuel`].
+    pub fuel: Option<usize>,
+    /// See [`Context::with_max_depth`].
+    pub max_depth: Option<usize>,
+    /// See [`Context::with_max_errors`].
+    pub max_errors: Option<usize>,
+}
+
+/// A saved location and state of a [`Context`], which can be restored with
+/// [`Context::rewind`].
+pub struct Checkpoint {
+    location: usize,
+    errors: usize,
+    states: StateCheckpoint,
 }
 
 impl<In: Slice, Error> Context<In, Error> {
@@ -18,7 +71,240 @@ impl<In: Slice, Error> Context<In, Error> {
             slice_current: slice,
 
             errors: vec![],
+            states: StateMap::default(),
+
+            depth: 0,
+            max_depth: None,
+            fuel: None,
+            max_errors: None,
+            make_error: None,
+            exhausted: false,
+            kept_errors: 0,
+            cut: false,
+
+            #[cfg(feature = "trace")]
+            tracer: None,
+        }
+    }
+
+    /// Apply some resource limits.
+    pub fn with_limits(mut self, limits: Limits) -> Self
+    where
+        Error: From<ParseError>,
+    {
+        self.max_depth = limits.max_depth;
+        self.fuel = limits.fuel;
+        self.max_errors = limits.max_errors;
+        self.make_error = Some(Error::from);
+        self
+    }
+
+    /// Limit the number of steps the parse can take, where each primitive
+    /// parser takes one step, reporting an [`ErrorKind::OutOfFuel`] error
+    /// and cutting the parse short once they run out.
+    ///
+    /// This bounds the time taken by a parse that backtracks a lot.
+    pub fn with_fuel(mut self, fuel: usize) -> Self
+    where
+        Error: From<ParseError>,
+    {
+        self.fuel = Some(fuel);
+        self.make_error = Some(Error::from);
+        self
+    }
+
+    /// The number of steps left, if limited with [`Context::with_fuel`].
+    pub fn fuel(&self) -> Option<usize> {
+        self.fuel
+    }
+
+    /// Stop parsing once `max_errors` errors have been reported, reporting an
+    /// [`ErrorKind::TooManyErrors`] error and cutting the parse short.
+    pub fn with_max_errors(mut self, max_errors: usize) -> Self
+    where
+        Error: From<ParseError>,
+    {
+        self.max_errors = Some(max_errors);
+        self.make_error = Some(Error::from);
+        self
+    }
+
+    /// Limit how deeply [`recursive`] parsers can be nested, reporting an
+    /// [`ErrorKind::NestingTooDeep`] error and cutting the parse short
+    /// instead of overflowing the stack.
+    ///
+    /// [`recursive`]: crate::recursive::recursive
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self
+    where
+        Error: From<ParseError>,
+    {
+        self.max_depth = Some(max_depth);
+        self.make_error = Some(Error::from);
+        self
+    }
+
+    /// How deeply [`recursive`] parsers are currently nested.
+    ///
+    /// [`recursive`]: crate::recursive::recursive
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Whether the parse was cut short by one of the limits, because the
+    /// fuel ran out, too many errors were reported, or parsers were nested
+    /// too deeply.
+    ///
+    /// The limit's error has been reported, and from then on every
+    /// primitive parser fails to match, so the parse ends quickly. Parsers
+    /// that loop without calling [`Context::step`] should stop once this is
+    /// set.
+    pub fn is_exhausted(&self) -> bool {
+        self.exhausted
+    }
+
+    /// Take a step, failing to match if the parse has been cut short or
+    /// there's no fuel left.
+    ///
+    /// Primitive parsers call this before doing anything else, so that the
+    /// limits set with [`Context::with_limits`] apply to them. Parsers that
+    /// are built from other parsers don't need to.
+    pub fn step(&mut self) -> ParseResult<(), Error> {
+        if self.exhausted {
+            return Err(None);
+        }
+
+        if let Some(fuel) = self.fuel {
+            if fuel == 0 {
+                self.exhaust(ErrorKind::OutOfFuel);
+                return Err(None);
+            }
+            self.fuel = Some(fuel - 1);
+        }
+
+        Ok(())
+    }
+
+    /// Cut the parse short, reporting an error that rewinding won't discard.
+    fn exhaust(&mut self, kind: ErrorKind) {
+        if let Some(make_error) = self.make_error {
+            let location = self.location();
+            let span = self.span(location, location);
+            self.errors.push(make_error(ParseError::new(kind, span)));
+        }
+        self.exhausted = true;
+        self.kept_errors = self.errors.len();
+    }
+
+    /// Run `f` one level deeper, failing if that's deeper than the limit.
+    pub(crate) fn nested<Out>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> ParseResult<Out, Error>,
+    ) -> ParseResult<Out, Error> {
+        if self.exhausted {
+            return Err(None);
+        }
+        if let Some(max_depth) = self.max_depth {
+            if self.depth >= max_depth {
+                self.exhaust(ErrorKind::NestingTooDeep { max_depth });
+                return Err(None);
+            }
+        }
+
+        self.depth += 1;
+        let result = f(self);
+        self.depth -= 1;
+        result
+    }
+
+    /// Record a [`Trace`] of the traced parsers and [`choice`] branches that
+    /// are run, to be taken with [`Context::take_trace`].
+    ///
+    /// This applies to the whole parse, but other parsers aren't recorded.
+    /// Wrap them with [`Parser::traced`] to record them too.
+    ///
+    /// [`choice`]: crate::combinator::choice::choice
+    /// [`Parser::traced`]: crate::parser::Parser::traced
+    #[cfg(feature = "trace")]
+    pub fn with_trace(mut self) -> Self {
+        self.tracer = Some(Tracer::new());
+        self
+    }
+
+    /// Take the trace recorded so far, if enabled with
+    /// [`Context::with_trace`], and stop tracing.
+    ///
+    /// Panics if called while a traced parser is running.
+    #[cfg(feature = "trace")]
+    pub fn take_trace(&mut self) -> Option<Trace<In>> {
+        self.tracer.take().map(Tracer::finish)
+    }
+
+    /// Run `f`, recording it as an event called `name` if tracing.
+    #[cfg(feature = "trace")]
+    pub(crate) fn trace<Out>(
+        &mut self,
+        name: &'static str,
+        f: impl FnOnce(&mut Self) -> ParseResult<Out, Error>,
+    ) -> ParseResult<Out, Error> {
+        let start = self.location();
+        match &mut self.tracer {
+            Some(tracer) => tracer.enter(name, start, self.slice_all),
+            None => return f(self),
+        }
+
+        let result = f(self);
+
+        let (outcome, end) = match &result {
+            Ok(_) => (Outcome::Matched, self.location()),
+            Err(None) => (Outcome::NoMatch, start),
+            Err(Some(_)) => (Outcome::Error, start),
+        };
+        let span = self.span(start, end);
+        let consumed = self
+            .slice_all
+            .slice(start, end)
+            .expect("couldn't get slice");
+        if let Some(tracer) = &mut self.tracer {
+            tracer.exit(outcome, span, consumed);
         }
+        result
+    }
+
+    #[cfg(not(feature = "trace"))]
+    #[inline(always)]
+    pub(crate) fn trace<Out>(
+        &mut self,
+        _name: &'static str,
+        f: impl FnOnce(&mut Self) -> ParseResult<Out, Error>,
+    ) -> ParseResult<Out, Error> {
+        f(self)
+    }
+
+    /// Add some user-defined state to the context.
+    ///
+    /// There can be one state of each type.
+    pub fn with_state<S: State>(mut self, state: S) -> Self {
+        self.insert_state(state);
+        self
+    }
+
+    /// Add some user-defined state to the context, returning the existing
+    /// state of the same type, if any.
+    pub fn insert_state<S: State>(&mut self, state: S) -> Option<S> {
+        self.states.insert(state)
+    }
+
+    /// Remove some user-defined state from the context.
+    pub fn take_state<S: State>(&mut self) -> Option<S> {
+        self.states.remove()
+    }
+
+    pub fn state<S: State>(&self) -> Option<&S> {
+        self.states.get()
+    }
+
+    pub fn state_mut<S: State>(&mut self) -> Option<&mut S> {
+        self.states.get_mut()
     }
 
     pub fn slice_all(&self) -> In {
@@ -34,33 +320,126 @@ impl<In: Slice, Error> Context<In, Error> {
     }
 
     pub fn set_location(&mut self, loc: usize) {
-        self.slice_current = self
-            .slice_all
-            .slice(loc, self.slice_all.len())
-            .expect("invalid location");
+        self.slice_current = self.slice_all.slice_from(loc).expect("invalid location");
+    }
+
+    /// Save the current location and state.
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            location: self.location(),
+            errors: self.errors.len(),
+            states: self.states.checkpoint(),
+        }
+    }
+
+    /// Restore the location and state saved in a checkpoint, discarding any
+    /// errors reported since.
+    pub fn rewind(&mut self, checkpoint: Checkpoint) {
+        self.set_location(checkpoint.location);
+        self.truncate_errors(checkpoint.errors);
+        self.states.rewind(checkpoint.states);
+    }
+
+    /// Run `f`, rewinding to the current checkpoint if it fails.
+    ///
+    /// This enforces the backtracking contract described on [`Parser::parse`]
+    /// for parsers that might not uphold it themselves.
+    ///
+    /// [`Parser::parse`]: crate::parser::Parser::parse
+    pub fn attempt<Out, E>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<Out, E>,
+    ) -> Result<Out, E> {
+        let start = self.checkpoint();
+        let result = f(self);
+        if result.is_err() {
+            self.rewind(start);
+        }
+        result
     }
 
+    pub(crate) fn is_cut(&self) -> bool {
+        self.cut
+    }
+
+    pub(crate) fn set_cut(&mut self, cut: bool) {
+        self.cut = cut;
+    }
+
+    /// Run `f` as if the input ended at `end`.
+    ///
+    /// Locations are unchanged, so spans still refer to the whole input.
+    pub(crate) fn with_end<Out>(&mut self, end: usize, f: impl FnOnce(&mut Self) -> Out) -> Out {
+        let slice_all = self.slice_all;
+        let location = self.location();
+        self.slice_all = slice_all.slice(0, end).expect("invalid location");
+        self.set_location(location);
+
+        let output = f(self);
+
+        let location = self.location();
+        self.slice_all = slice_all;
+        self.set_location(location);
+        output
+    }
+
+    /// Get the span in the source from `start` to `end`.
+    ///
+    /// See [`Slice::source_span`].
+    pub fn span(&self, start: usize, end: usize) -> Span {
+        self.slice_all.source_span(start, end)
+    }
+
+    /// Get the span in the source from `start` to the current location.
+    pub fn span_from(&self, start: usize) -> Span {
+        self.span(start, self.location())
+    }
+
+    /// Report an error, unless the parse has been cut short.
     pub fn report(&mut self, error: Error) {
+        if self.exhausted {
+            return;
+        }
         self.errors.push(error);
+        if let Some(max_errors) = self.max_errors {
+            if self.errors.len() >= max_errors {
+                self.exhaust(ErrorKind::TooManyErrors { max_errors });
+            }
+        }
     }
 
     pub fn errors(&self) -> &[Error] {
         &self.errors
     }
 
-    pub(crate) fn result_to_errors<Out>(
-        mut self,
-        result: ParseResult<Out, Error>,
-    ) -> (Option<Out>, Vec<Error>) {
-        let output = match result {
+    /// Discard the errors reported after the first `len`, except those
+    /// kept when the parse was cut short.
+    pub(crate) fn truncate_errors(&mut self, len: usize) {
+        self.errors.truncate(len.max(self.kept_errors));
+    }
+
+    /// Take the errors reported after the first `len`, except those kept
+    /// when the parse was cut short.
+    pub(crate) fn split_off_errors(&mut self, len: usize) -> Vec<Error> {
+        self.errors.split_off(len.max(self.kept_errors))
+    }
+
+    /// Take the errors reported so far.
+    pub fn take_errors(&mut self) -> Vec<Error> {
+        self.kept_errors = 0;
+        std::mem::take(&mut self.errors)
+    }
+
+    /// Report the error in a result, if any.
+    pub(crate) fn report_result<Out>(&mut self, result: ParseResult<Out, Error>) -> Option<Out> {
+        match result {
             Ok(output) => Some(output),
             Err(Some(err)) => {
                 self.report(err);
                 None
             }
             Err(None) => None,
-        };
-        (output, self.errors)
+        }
     }
 }
 
@@ -0,0 +1,39 @@
+//! Parsing [`ropey`] ropes, such as editor buffers, without copying them.
+//!
+//! Use a [`RopeSlice`] as input (from [`ropey::Rope::slice`]). Locations
+//! are byte offsets, as they are for `&str`, and outputs such as those of
+//! [`Parser::to_slice`] are also [`RopeSlice`]s.
+//!
+//! [`Parser::to_slice`]: crate::parser::Parser::to_slice
+
+use ropey::RopeSlice;
+
+use super::slice::Slice;
+
+impl Slice for RopeSlice<'_> {
+    type Token = char;
+
+    fn next(&self) -> Option<(Self::Token, Self)> {
+        let c = self.chars().next()?;
+        Some((c, self.byte_slice(c.len_utf8()..)))
+    }
+
+    fn slice(&self, start: usize, end: usize) -> Option<Self> {
+        self.get_byte_slice(start..end)
+    }
+
+    fn len(&self) -> usize {
+        self.len_bytes()
+    }
+
+    fn prefix_len(&self, mut pred: impl FnMut(Self::Token) -> bool) -> usize {
+        let mut len = 0;
+        for chunk in self.chunks() {
+            match chunk.char_indices().find(|&(_, c)| !pred(c)) {
+                Some((i, _)) => return len + i,
+                None => len += chunk.len(),
+            }
+        }
+        len
+    }
+}
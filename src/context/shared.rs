@@ -0,0 +1,270 @@
+//! Input that is owned by an [`Arc`], so that parts of it can be output
+//! without borrowing from the input.
+//!
+//! Parse a [`Shared`] with [`Shared::parse`]. The input is a
+//! [`SharedSlice`], which can be turned into an owned [`Shared`] with
+//! [`SharedSlice::to_shared`] without copying the text.
+
+use std::borrow::{Borrow, Cow};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::sync::Arc;
+
+use derive_where::derive_where;
+
+use super::slice::Slice;
+use super::span::Span;
+use crate::parser::Parser;
+
+/// A shared, owned string or slice (such as `str` or `[T]`), or part of one.
+///
+/// Cloning only clones the [`Arc`].
+#[derive_where(Clone)]
+pub struct Shared<S: ?Sized> {
+    source: Arc<S>,
+    start: usize,
+    end: usize,
+}
+
+impl<S: ?Sized> Shared<S>
+where
+    for<'a> &'a S: Slice,
+{
+    pub fn new(source: Arc<S>) -> Self {
+        let end = source.as_ref().len();
+        Self {
+            source,
+            start: 0,
+            end,
+        }
+    }
+
+    /// Get the part of the source this refers to.
+    pub fn get(&self) -> &S {
+        self.source
+            .as_ref()
+            .slice(self.start, self.end)
+            .expect("couldn't get slice")
+    }
+
+    /// The whole source that this is part of.
+    pub fn source(&self) -> &Arc<S> {
+        &self.source
+    }
+
+    /// The location of this in its source.
+    pub fn span(&self) -> Span {
+        Span::new(self.start, self.end)
+    }
+
+    /// Get this as parser input.
+    pub fn as_slice(&self) -> SharedSlice<'_, S> {
+        SharedSlice {
+            source: &self.source,
+            start: self.start,
+            end: self.end,
+        }
+    }
+
+    /// Run a parser on this.
+    ///
+    /// Parts of the input can be output with [`SharedSlice::to_shared`], so
+    /// that the output doesn't borrow from `self`.
+    pub fn parse<'a, Out, Error>(
+        &'a self,
+        parser: &impl Parser<SharedSlice<'a, S>, Out, Error>,
+    ) -> (Option<Out>, Vec<Error>) {
+        parser.parse_input(self.as_slice())
+    }
+}
+
+impl<S: ?Sized> Deref for Shared<S>
+where
+    for<'a> &'a S: Slice,
+{
+    type Target = S;
+
+    fn deref(&self) -> &S {
+        self.get()
+    }
+}
+
+impl<S: ?Sized> AsRef<S> for Shared<S>
+where
+    for<'a> &'a S: Slice,
+{
+    fn as_ref(&self) -> &S {
+        self.get()
+    }
+}
+
+impl Borrow<str> for Shared<str> {
+    fn borrow(&self) -> &str {
+        self.get()
+    }
+}
+
+impl<S: ?Sized + fmt::Debug> fmt::Debug for Shared<S>
+where
+    for<'a> &'a S: Slice,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.get().fmt(f)
+    }
+}
+
+impl<S: ?Sized + fmt::Display> fmt::Display for Shared<S>
+where
+    for<'a> &'a S: Slice,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.get().fmt(f)
+    }
+}
+
+impl<S: ?Sized + PartialEq> PartialEq for Shared<S>
+where
+    for<'a> &'a S: Slice,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.get() == other.get()
+    }
+}
+
+impl<S: ?Sized + Eq> Eq for Shared<S> where for<'a> &'a S: Slice {}
+
+impl<S: ?Sized + PartialEq> PartialEq<S> for Shared<S>
+where
+    for<'a> &'a S: Slice,
+{
+    fn eq(&self, other: &S) -> bool {
+        self.get() == other
+    }
+}
+
+impl PartialEq<&str> for Shared<str> {
+    fn eq(&self, other: &&str) -> bool {
+        self.get() == *other
+    }
+}
+
+impl<S: ?Sized + Hash> Hash for Shared<S>
+where
+    for<'a> &'a S: Slice,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.get().hash(state);
+    }
+}
+
+impl<S: ?Sized> From<Arc<S>> for Shared<S>
+where
+    for<'a> &'a S: Slice,
+{
+    fn from(source: Arc<S>) -> Self {
+        Self::new(source)
+    }
+}
+
+impl From<&str> for Shared<str> {
+    fn from(source: &str) -> Self {
+        Self::new(source.into())
+    }
+}
+
+impl From<String> for Shared<str> {
+    fn from(source: String) -> Self {
+        Self::new(source.into())
+    }
+}
+
+impl From<Cow<'_, str>> for Shared<str> {
+    fn from(source: Cow<'_, str>) -> Self {
+        Self::new(source.into())
+    }
+}
+
+impl<T: Copy> From<&[T]> for Shared<[T]> {
+    fn from(source: &[T]) -> Self {
+        Self::new(source.into())
+    }
+}
+
+impl<T: Copy> From<Vec<T>> for Shared<[T]> {
+    fn from(source: Vec<T>) -> Self {
+        Self::new(source.into())
+    }
+}
+
+/// Parser input borrowed from a [`Shared`].
+///
+/// Locations are relative to the start of the [`Shared`] it was taken from,
+/// but spans (such as those from [`Parser::spanned`]) are relative to the
+/// start of the source.
+#[derive_where(Clone, Copy)]
+#[derive_where(Debug; S: fmt::Debug)]
+pub struct SharedSlice<'a, S: ?Sized> {
+    source: &'a Arc<S>,
+    start: usize,
+    end: usize,
+}
+
+impl<'a, S: ?Sized> SharedSlice<'a, S>
+where
+    &'a S: Slice,
+{
+    pub fn get(&self) -> &'a S {
+        self.source
+            .as_ref()
+            .slice(self.start, self.end)
+            .expect("couldn't get slice")
+    }
+
+    /// Make an owned copy of this, which shares the source.
+    pub fn to_shared(&self) -> Shared<S> {
+        Shared {
+            source: self.source.clone(),
+            start: self.start,
+            end: self.end,
+        }
+    }
+}
+
+impl<'a, S: ?Sized> Slice for SharedSlice<'a, S>
+where
+    &'a S: Slice,
+{
+    type Token = <&'a S as Slice>::Token;
+
+    fn next(&self) -> Option<(Self::Token, Self)> {
+        let (token, rest) = self.get().next()?;
+        let rest = Self {
+            start: self.end - rest.len(),
+            ..*self
+        };
+        Some((token, rest))
+    }
+
+    fn slice(&self, start: usize, end: usize) -> Option<Self> {
+        // Check that the slice is valid, such as on a character boundary.
+        self.get().slice(start, end)?;
+        Some(Self {
+            source: self.source,
+            start: self.start + start,
+            end: self.start + end,
+        })
+    }
+
+    fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    fn prefix_len(&self, pred: impl FnMut(Self::Token) -> bool) -> usize {
+        self.get().prefix_len(pred)
+    }
+
+    fn source_span(&self, start: usize, end: usize) -> Span {
+        Span::new(self.start + start, self.start + end)
+    }
+}
@@ -1,3 +1,5 @@
+use super::span::Span;
+
 pub trait Slice: Sized + Copy {
     type Token: Copy;
 
@@ -7,12 +9,43 @@ pub trait Slice: Sized + Copy {
 
     fn len(&self) -> usize;
 
+    /// Get the slice from `start` to the end.
+    ///
+    /// Implementations whose length can grow (such as streams) can override
+    /// this to keep the end open.
+    fn slice_from(&self, start: usize) -> Option<Self> {
+        self.slice(start, self.len())
+    }
+
     fn is_empty(&self) -> bool {
         self.len() == 0
     }
+
+    /// Get the span in the source of the tokens from `start` to `end`.
+    ///
+    /// By default this is just `start..end`, but slices of tokens from a
+    /// separate lexer can override it to point into the original source.
+    fn source_span(&self, start: usize, end: usize) -> Span {
+        Span::new(start, end)
+    }
+
+    /// Get the length of the longest prefix where every token matches `pred`.
+    ///
+    /// Implementations can override this to scan faster than repeatedly
+    /// calling [`Slice::next`].
+    fn prefix_len(&self, mut pred: impl FnMut(Self::Token) -> bool) -> usize {
+        let mut rest = *self;
+        while let Some((token, next)) = rest.next() {
+            if !pred(token) {
+                break;
+            }
+            rest = next;
+        }
+        self.len() - rest.len()
+    }
 }
 
-impl<'a, T: Copy> Slice for &'a [T] {
+impl<T: Copy> Slice for &[T] {
     type Token = T;
 
     fn next(&self) -> Option<(Self::Token, Self)> {
@@ -29,9 +62,15 @@ impl<'a, T: Copy> Slice for &'a [T] {
     fn len(&self) -> usize {
         <[T]>::len(self)
     }
+
+    fn prefix_len(&self, mut pred: impl FnMut(Self::Token) -> bool) -> usize {
+        self.iter()
+            .position(|&token| !pred(token))
+            .unwrap_or(<[T]>::len(self))
+    }
 }
 
-impl<'a> Slice for &'a str {
+impl Slice for &str {
     type Token = char;
 
     fn next(&self) -> Option<(Self::Token, Self)> {
@@ -46,4 +85,24 @@ impl<'a> Slice for &'a str {
     fn len(&self) -> usize {
         str::len(self)
     }
+
+    fn prefix_len(&self, mut pred: impl FnMut(Self::Token) -> bool) -> usize {
+        // ASCII chars are read straight from the bytes, and only the others
+        // need decoding.
+        let bytes = self.as_bytes();
+        let mut len = 0;
+        while let Some(&byte) = bytes.get(len) {
+            let (c, c_len) = if byte.is_ascii() {
+                (char::from(byte), 1)
+            } else {
+                let c = self[len..].chars().next().expect("not at a char boundary");
+                (c, c.len_utf8())
+            };
+            if !pred(c) {
+                break;
+            }
+            len += c_len;
+        }
+        len
+    }
 }
@@ -0,0 +1,163 @@
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// User-defined state that can be stored in a [`Context`].
+///
+/// When a parser fails, the context is rewound to where the parser started,
+/// and so is any state stored in it. Checkpoints are taken from every state
+/// before a parser that might backtrack, and passed back to
+/// [`State::rewind`] if it does.
+///
+/// Checkpoints are only taken again once a state has been borrowed
+/// mutably, so the same checkpoint may be used to rewind several times.
+///
+/// State that doesn't need to be rewound (such as an interner) can use `()`
+/// as its checkpoint.
+///
+/// [`Context`]: super::Context
+pub trait State: 'static {
+    type Checkpoint: Clone + 'static;
+
+    /// Take a checkpoint of the current state.
+    fn checkpoint(&self) -> Self::Checkpoint;
+
+    /// Restore the state to a previous checkpoint.
+    fn rewind(&mut self, checkpoint: Self::Checkpoint);
+}
+
+trait ErasedState {
+    fn checkpoint(&self) -> Box<dyn Any>;
+
+    fn rewind(&mut self, checkpoint: &dyn Any);
+
+    fn as_any(&self) -> &dyn Any;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
+}
+
+impl<S: State> ErasedState for S {
+    fn checkpoint(&self) -> Box<dyn Any> {
+        Box::new(State::checkpoint(self))
+    }
+
+    fn rewind(&mut self, checkpoint: &dyn Any) {
+        let checkpoint = checkpoint
+            .downcast_ref::<S::Checkpoint>()
+            .expect("checkpoint taken from a different state");
+        State::rewind(self, checkpoint.clone());
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+}
+
+/// A map of states, one per type.
+#[derive(Default)]
+pub(crate) struct StateMap {
+    states: Vec<(TypeId, Box<dyn ErasedState>)>,
+
+    /// Incremented whenever a state might have changed.
+    generation: u64,
+    /// The checkpoints taken at some generation, shared by every
+    /// [`StateCheckpoint`] taken until the states change again.
+    cached: RefCell<Option<(u64, Checkpoints)>>,
+}
+
+type Checkpoints = Rc<[(TypeId, Box<dyn Any>)]>;
+
+/// Checkpoints of every state in a [`StateMap`].
+pub(crate) struct StateCheckpoint {
+    generation: u64,
+    checkpoints: Option<Checkpoints>,
+}
+
+impl StateMap {
+    pub fn insert<S: State>(&mut self, state: S) -> Option<S> {
+        let old = self.remove::<S>();
+        self.generation += 1;
+        self.states.push((TypeId::of::<S>(), Box::new(state)));
+        old
+    }
+
+    pub fn remove<S: State>(&mut self) -> Option<S> {
+        let index = self.index_of::<S>()?;
+        self.generation += 1;
+        let (_, state) = self.states.remove(index);
+        let state = state.into_any().downcast().expect("state has wrong type");
+        Some(*state)
+    }
+
+    pub fn get<S: State>(&self) -> Option<&S> {
+        let index = self.index_of::<S>()?;
+        self.states[index].1.as_any().downcast_ref()
+    }
+
+    pub fn get_mut<S: State>(&mut self) -> Option<&mut S> {
+        let index = self.index_of::<S>()?;
+        self.generation += 1;
+        self.states[index].1.as_any_mut().downcast_mut()
+    }
+
+    /// Take checkpoints of every state, reusing the last ones if the states
+    /// haven't changed since.
+    pub fn checkpoint(&self) -> StateCheckpoint {
+        if self.states.is_empty() {
+            return StateCheckpoint {
+                generation: self.generation,
+                checkpoints: None,
+            };
+        }
+
+        let mut cached = self.cached.borrow_mut();
+        let checkpoints = match &*cached {
+            Some((generation, checkpoints)) if *generation == self.generation => {
+                checkpoints.clone()
+            }
+            _ => {
+                let checkpoints: Checkpoints = self
+                    .states
+                    .iter()
+                    .map(|(id, state)| (*id, state.checkpoint()))
+                    .collect();
+                *cached = Some((self.generation, checkpoints.clone()));
+                checkpoints
+            }
+        };
+
+        StateCheckpoint {
+            generation: self.generation,
+            checkpoints: Some(checkpoints),
+        }
+    }
+
+    /// Rewind every state that existed when the checkpoint was taken.
+    pub fn rewind(&mut self, checkpoint: StateCheckpoint) {
+        if checkpoint.generation == self.generation {
+            return;
+        }
+
+        for (id, checkpoint) in checkpoint.checkpoints.iter().flat_map(|c| c.iter()) {
+            if let Some((_, state)) = self.states.iter_mut().find(|(other, _)| other == id) {
+                state.rewind(checkpoint.as_ref());
+            }
+        }
+        self.generation += 1;
+    }
+
+    fn index_of<S: State>(&self) -> Option<usize> {
+        let id = TypeId::of::<S>();
+        self.states.iter().position(|(other, _)| *other == id)
+    }
+}
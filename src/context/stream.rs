@@ -0,0 +1,488 @@
+//! Input that is read incrementally, such as from a file or a socket.
+//!
+//! A [`Stream`] reads its input into a buffer as parsers need it. Parse one
+//! item at a time with [`Stream::parse_next`], which drops the input it
+//! consumes, so that memory use is bounded by the size of the largest item
+//! rather than the whole input.
+
+use std::cell::RefCell;
+use std::fmt::{self, Write};
+use std::io::{self, Read};
+use std::marker::PhantomData;
+
+use derive_where::derive_where;
+
+use super::slice::Slice;
+use super::span::Span;
+use super::Context;
+use crate::error::{ErrorKind, ParseError};
+use crate::parser::Parser;
+
+/// Where a [`Stream`] reads its input from.
+pub trait Source {
+    /// Append some more input to `buffer`.
+    fn fill(&mut self, buffer: &mut Vec<u8>) -> Fill;
+}
+
+/// The result of [`Source::fill`].
+#[derive(Debug)]
+pub enum Fill {
+    /// Some input was added (possibly none, in which case `fill` is called
+    /// again).
+    More,
+    /// There is no more input.
+    End,
+    /// There is no more input yet, but there may be later.
+    Pending,
+    /// Reading failed. No more input is read, and the error is reported by
+    /// [`Stream::parse_next`].
+    Error(io::Error),
+}
+
+/// A [`Source`] that reads from an [`io::Read`].
+#[derive(Debug)]
+pub struct ReadSource<R> {
+    reader: R,
+}
+
+impl<R: Read> Source for ReadSource<R> {
+    fn fill(&mut self, buffer: &mut Vec<u8>) -> Fill {
+        const CHUNK_SIZE: usize = 8 * 1024;
+
+        let len = buffer.len();
+        buffer.resize(len + CHUNK_SIZE, 0);
+        let result = loop {
+            match self.reader.read(&mut buffer[len..]) {
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                result => break result,
+            }
+        };
+
+        match result {
+            Ok(0) => {
+                buffer.truncate(len);
+                Fill::End
+            }
+            Ok(n) => {
+                buffer.truncate(len + n);
+                Fill::More
+            }
+            Err(err) => {
+                buffer.truncate(len);
+                Fill::Error(err)
+            }
+        }
+    }
+}
+
+/// A [`Source`] that reads from an iterator of chunks.
+#[derive(Debug)]
+pub struct Chunks<I> {
+    chunks: I,
+}
+
+impl<I> Source for Chunks<I>
+where
+    I: Iterator,
+    I::Item: AsRef<[u8]>,
+{
+    fn fill(&mut self, buffer: &mut Vec<u8>) -> Fill {
+        match self.chunks.next() {
+            Some(chunk) => {
+                buffer.extend_from_slice(chunk.as_ref());
+                Fill::More
+            }
+            None => Fill::End,
+        }
+    }
+}
+
+/// A [`Source`] whose input is added with [`Stream::feed`], for parsing
+/// input as it arrives without blocking.
+#[derive(Debug, Default)]
+pub struct Pending {
+    closed: bool,
+}
+
+impl Source for Pending {
+    fn fill(&mut self, _buffer: &mut Vec<u8>) -> Fill {
+        if self.closed {
+            Fill::End
+        } else {
+            Fill::Pending
+        }
+    }
+}
+
+/// Input that is read from a [`Source`] as it's needed.
+///
+/// Parse it with [`Stream::parse_next`], or with a [`StreamSlice`] from
+/// [`Stream::slice`]. Locations are byte offsets from the start of the
+/// unconsumed input, but spans are byte offsets from the start of the
+/// stream.
+#[derive(Debug)]
+pub struct Stream<S> {
+    buffer: RefCell<Buffer<S>>,
+}
+
+#[derive(Debug)]
+struct Buffer<S> {
+    source: S,
+    bytes: Vec<u8>,
+    /// The offset in the stream of `bytes[0]`.
+    base: usize,
+    /// The offset in the stream of the first byte that hasn't been
+    /// consumed.
+    start: usize,
+    ended: bool,
+    /// Whether a parser has tried to read input that isn't available yet.
+    incomplete: bool,
+    /// The message of the error that stopped the input from being read,
+    /// which stays after the error is taken.
+    failure: Option<String>,
+    error: Option<io::Error>,
+}
+
+impl<S: Source> Buffer<S> {
+    fn end(&self) -> usize {
+        self.base + self.bytes.len()
+    }
+
+    /// Read input until the byte before `offset` is buffered, returning
+    /// whether it is.
+    fn fill_to(&mut self, offset: usize) -> bool {
+        while self.end() < offset {
+            if self.ended {
+                return false;
+            }
+
+            match self.source.fill(&mut self.bytes) {
+                Fill::More => {}
+                Fill::End => self.ended = true,
+                Fill::Pending => {
+                    self.incomplete = true;
+                    return false;
+                }
+                Fill::Error(err) => {
+                    self.failure = Some(err.to_string());
+                    self.error = Some(err);
+                    self.ended = true;
+                }
+            }
+        }
+        true
+    }
+
+    fn bytes(&self, start: usize, end: usize) -> &[u8] {
+        assert!(start >= self.base, "stream input was already consumed");
+        &self.bytes[start - self.base..end - self.base]
+    }
+
+    fn consume(&mut self, len: usize) {
+        self.start += len;
+
+        // Only move the remaining input to the front once enough of it has
+        // been consumed, so that this takes amortised constant time.
+        let consumed = self.start - self.base;
+        if consumed > 0 && consumed >= self.bytes.len() / 2 {
+            self.bytes.drain(..consumed);
+            self.base = self.start;
+        }
+    }
+}
+
+impl<S: Source> Stream<S> {
+    pub fn new(source: S) -> Self {
+        Self {
+            buffer: RefCell::new(Buffer {
+                source,
+                bytes: vec![],
+                base: 0,
+                start: 0,
+                ended: false,
+                incomplete: false,
+                failure: None,
+                error: None,
+            }),
+        }
+    }
+
+    /// Get the unconsumed input, as bytes (with `u8` tokens) or as UTF-8
+    /// text (with `char` tokens).
+    ///
+    /// The slice is invalidated when input is consumed, and using it after
+    /// that may panic.
+    pub fn slice<T: StreamToken>(&self) -> StreamSlice<'_, S, T> {
+        StreamSlice {
+            stream: self,
+            start: self.buffer.borrow().start,
+            end: None,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Drop the first `len` bytes of the unconsumed input.
+    pub fn consume(&self, len: usize) {
+        self.buffer.borrow_mut().consume(len);
+    }
+
+    /// The offset in the stream of the unconsumed input.
+    pub fn offset(&self) -> usize {
+        self.buffer.borrow().start
+    }
+
+    /// The number of bytes held in memory.
+    pub fn buffered(&self) -> usize {
+        self.buffer.borrow().bytes.len()
+    }
+
+    /// Take the error that stopped the input from being read, if any.
+    pub fn take_error(&self) -> Option<io::Error> {
+        self.buffer.borrow_mut().error.take()
+    }
+
+    /// Parse the unconsumed input with `parser`, consuming the input it
+    /// matches if it succeeds.
+    ///
+    /// If the parser needs input that hasn't arrived yet, nothing is
+    /// consumed and an [`ErrorKind::Incomplete`] error is reported instead.
+    /// Add more input with [`Stream::feed`] and try again. Similarly, once
+    /// the input couldn't be read, nothing is consumed and an
+    /// [`ErrorKind::ReadFailed`] error is reported by this and every later
+    /// call, rather than treating the rest of the input as missing.
+    ///
+    /// The output can't borrow from the stream, since the input it was
+    /// parsed from is consumed. Copy slices of the input with
+    /// [`StreamSlice::to_vec`] or [`ToString::to_string`].
+    pub fn parse_next<'a, T, Out, Error>(
+        &'a self,
+        parser: &impl Parser<StreamSlice<'a, S, T>, Out, Error>,
+    ) -> (Option<Out>, Vec<Error>)
+    where
+        T: StreamToken,
+        Out: 'static,
+        Error: From<ParseError>,
+    {
+        self.buffer.borrow_mut().incomplete = false;
+        let mut context = Context::new(self.slice());
+        let result = parser.parse(&mut context);
+
+        let buffer = self.buffer.borrow();
+        let span = Span::new(buffer.start, buffer.end());
+        if buffer.incomplete {
+            let error = ParseError::new(ErrorKind::Incomplete, span);
+            return (None, vec![error.into()]);
+        }
+        if let Some(message) = &buffer.failure {
+            let kind = ErrorKind::ReadFailed {
+                message: message.clone(),
+            };
+            return (None, vec![ParseError::new(kind, span).into()]);
+        }
+        drop(buffer);
+
+        let output = context.report_result(result);
+        if output.is_some() {
+            self.consume(context.location());
+        }
+        (output, context.take_errors())
+    }
+}
+
+impl<R: Read> Stream<ReadSource<R>> {
+    pub fn from_read(reader: R) -> Self {
+        Self::new(ReadSource { reader })
+    }
+}
+
+impl<I: Iterator> Stream<Chunks<I>>
+where
+    I::Item: AsRef<[u8]>,
+{
+    pub fn from_chunks(chunks: impl IntoIterator<IntoIter = I>) -> Self {
+        Self::new(Chunks {
+            chunks: chunks.into_iter(),
+        })
+    }
+}
+
+impl Stream<Pending> {
+    /// Create a stream whose input is added with [`Stream::feed`].
+    pub fn pending() -> Self {
+        Self::new(Pending::default())
+    }
+
+    /// Add some input.
+    pub fn feed(&self, input: &[u8]) {
+        self.buffer.borrow_mut().bytes.extend_from_slice(input);
+    }
+
+    /// Mark the end of the input.
+    pub fn close(&self) {
+        self.buffer.borrow_mut().source.closed = true;
+    }
+}
+
+mod sealed {
+    pub trait Sealed {}
+
+    impl Sealed for u8 {}
+    impl Sealed for char {}
+}
+
+/// A token that can be read from a [`Stream`]: `u8` or `char`.
+pub trait StreamToken: Copy + sealed::Sealed {
+    /// Decode a token from the start of `bytes`, outputting it and its
+    /// length, or `None` if more bytes are needed. If `complete`, there
+    /// are no more bytes.
+    #[doc(hidden)]
+    fn decode(bytes: &[u8], complete: bool) -> Option<(Self, usize)>;
+}
+
+impl StreamToken for u8 {
+    fn decode(bytes: &[u8], _complete: bool) -> Option<(Self, usize)> {
+        Some((bytes[0], 1))
+    }
+}
+
+impl StreamToken for char {
+    /// Invalid UTF-8 is decoded as `U+FFFD`, one byte at a time.
+    fn decode(bytes: &[u8], complete: bool) -> Option<(Self, usize)> {
+        let bytes = &bytes[..bytes.len().min(4)];
+        let (valid, error) = match std::str::from_utf8(bytes) {
+            Ok(valid) => (valid, None),
+            Err(err) => (
+                std::str::from_utf8(&bytes[..err.valid_up_to()]).expect("invalid UTF-8"),
+                Some(err),
+            ),
+        };
+
+        match (valid.chars().next(), error) {
+            (Some(c), _) => Some((c, c.len_utf8())),
+            (None, Some(err)) if err.error_len().is_none() && !complete => None,
+            (None, _) => Some((char::REPLACEMENT_CHARACTER, 1)),
+        }
+    }
+}
+
+/// A view of the input of a [`Stream`].
+///
+/// Reading past the end of the buffered input reads more from the stream.
+/// Slices of text display as their text, with invalid UTF-8 replaced by
+/// `U+FFFD`.
+#[derive_where(Clone, Copy)]
+#[derive_where(Debug)]
+pub struct StreamSlice<'a, S, T> {
+    #[derive_where(skip)]
+    stream: &'a Stream<S>,
+    start: usize,
+    /// The end of the slice, or `None` if it extends to the end of the
+    /// stream.
+    end: Option<usize>,
+    _phantom: PhantomData<T>,
+}
+
+impl<S: Source, T: StreamToken> StreamSlice<'_, S, T> {
+    /// Copy the bytes of the slice.
+    ///
+    /// A slice that extends to the end of the stream reads the rest of the
+    /// stream first.
+    pub fn to_vec(&self) -> Vec<u8> {
+        let mut buffer = self.stream.buffer.borrow_mut();
+        let end = match self.end {
+            Some(end) => end,
+            None => {
+                buffer.fill_to(usize::MAX);
+                buffer.end()
+            }
+        };
+        buffer.bytes(self.start, end).to_vec()
+    }
+}
+
+impl<S: Source> fmt::Display for StreamSlice<'_, S, char> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut rest = *self;
+        while let Some((c, next)) = rest.next() {
+            f.write_char(c)?;
+            rest = next;
+        }
+        Ok(())
+    }
+}
+
+impl<S: Source, T: StreamToken> Slice for StreamSlice<'_, S, T> {
+    type Token = T;
+
+    fn next(&self) -> Option<(Self::Token, Self)> {
+        let mut buffer = self.stream.buffer.borrow_mut();
+        let mut want = self.start + 1;
+
+        loop {
+            let filled = buffer.fill_to(want);
+            let end = self.end.map_or(buffer.end(), |end| end.min(buffer.end()));
+            if self.start >= end {
+                return None;
+            }
+
+            let complete = !filled || self.end.is_some_and(|end| end <= buffer.end());
+            match T::decode(buffer.bytes(self.start, end), complete) {
+                Some((token, len)) => {
+                    let rest = Self {
+                        start: self.start + len,
+                        ..*self
+                    };
+                    return Some((token, rest));
+                }
+                None => want = buffer.end() + 1,
+            }
+        }
+    }
+
+    fn slice(&self, start: usize, end: usize) -> Option<Self> {
+        if self.end.is_none() {
+            self.stream.buffer.borrow_mut().fill_to(self.start + end);
+        }
+        if start > end || end > self.len() {
+            return None;
+        }
+
+        Some(Self {
+            start: self.start + start,
+            end: Some(self.start + end),
+            ..*self
+        })
+    }
+
+    fn slice_from(&self, start: usize) -> Option<Self> {
+        if self.end.is_none() {
+            self.stream.buffer.borrow_mut().fill_to(self.start + start);
+        }
+        if start > self.len() {
+            return None;
+        }
+
+        Some(Self {
+            start: self.start + start,
+            ..*self
+        })
+    }
+
+    fn len(&self) -> usize {
+        let end = self
+            .end
+            .unwrap_or_else(|| self.stream.buffer.borrow().end());
+        end - self.start
+    }
+
+    fn is_empty(&self) -> bool {
+        match self.end {
+            Some(end) => end == self.start,
+            None => !self.stream.buffer.borrow_mut().fill_to(self.start + 1),
+        }
+    }
+
+    fn source_span(&self, start: usize, end: usize) -> Span {
+        Span::new(self.start + start, self.start + end)
+    }
+}
@@ -0,0 +1,93 @@
+use super::slice::Slice;
+use super::span::Span;
+
+/// A slice of tokens from a separate lexer, each with its span in the
+/// source.
+///
+/// Locations in the [`Context`](super::Context) are still token indices,
+/// but spans (such as those from [`Parser::spanned`] and in errors) point
+/// into the source.
+///
+/// [`Parser::spanned`]: crate::parser::Parser::spanned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenSlice<'a, T> {
+    tokens: &'a [(T, Span)],
+    /// Where the slice ends in the source, for the span of an empty slice.
+    end: usize,
+}
+
+impl<'a, T> TokenSlice<'a, T> {
+    /// Wrap some tokens.
+    ///
+    /// The end of the input is taken to be the end of the last token.
+    pub fn new(tokens: &'a [(T, Span)]) -> Self {
+        let end = tokens.last().map_or(0, |(_, span)| span.end());
+        Self { tokens, end }
+    }
+
+    /// Wrap some tokens, with the end of the input at `eof` in the source.
+    ///
+    /// This gives a more useful span for errors at the end of the input,
+    /// such as when there is trailing whitespace or a comment.
+    pub fn with_eof(tokens: &'a [(T, Span)], eof: usize) -> Self {
+        Self { tokens, end: eof }
+    }
+
+    pub fn tokens(&self) -> &'a [(T, Span)] {
+        self.tokens
+    }
+
+    /// Get the span of these tokens in the source.
+    pub fn span(&self) -> Span {
+        self.span_of(0, self.tokens.len())
+    }
+
+    fn span_of(&self, start: usize, end: usize) -> Span {
+        if start < end {
+            Span::new(self.tokens[start].1.start(), self.tokens[end - 1].1.end())
+        } else {
+            let point = self.point(start);
+            Span::new(point, point)
+        }
+    }
+
+    /// Get the location in the source before the token at `index`.
+    fn point(&self, index: usize) -> usize {
+        self.tokens
+            .get(index)
+            .map_or(self.end, |(_, span)| span.start())
+    }
+}
+
+impl<T: Copy> Slice for TokenSlice<'_, T> {
+    type Token = T;
+
+    fn next(&self) -> Option<(Self::Token, Self)> {
+        let ((token, _), rest) = self.tokens.split_first()?;
+        Some((
+            *token,
+            Self {
+                tokens: rest,
+                end: self.end,
+            },
+        ))
+    }
+
+    fn slice(&self, start: usize, end: usize) -> Option<Self> {
+        let tokens = self.tokens.get(start..end)?;
+        let end = if start < end {
+            tokens[tokens.len() - 1].1.end()
+        } else {
+            self.point(start)
+        };
+        Some(Self { tokens, end })
+    }
+
+    fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    fn source_span(&self, start: usize, end: usize) -> Span {
+        self.span_of(start, end)
+    }
+}
@@ -0,0 +1,156 @@
+use std::fmt;
+
+use crate::context::span::Span;
+
+/// An error produced by one of papa's built-in parsers.
+///
+/// Parsers that need to report errors require `Error: From<ParseError>`, so
+/// that they can be used with any error type that can represent them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    kind: ErrorKind,
+    span: Span,
+}
+
+impl ParseError {
+    pub fn new(kind: ErrorKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// A repeated parser matched without consuming any input.
+    ZeroWidthRepeat { label: &'static str },
+
+    /// A repeated parser matched fewer times than required.
+    TooFewRepeats { expected: usize, found: usize },
+
+    /// The input wasn't fully consumed.
+    TrailingInput,
+
+    /// An integer literal doesn't fit in the output type.
+    IntegerOutOfRange,
+
+    /// An escape sequence in a string literal is invalid.
+    InvalidEscape,
+
+    /// A string literal contains an unescaped control character.
+    ControlCharacter,
+
+    /// A string literal is missing its closing quote.
+    UnterminatedString,
+
+    /// A block comment is missing its closing delimiter.
+    UnterminatedComment,
+
+    /// A reserved keyword was used where an identifier was expected.
+    KeywordAsIdent { keyword: String },
+
+    /// A line was indented further than the block it's in.
+    UnexpectedIndent,
+
+    /// A line was dedented to a level that doesn't match any enclosing block.
+    InconsistentDedent,
+
+    /// A token didn't match any of the expected patterns.
+    Expected { expected: Vec<&'static str> },
+
+    /// Recursive parsers were nested more deeply than allowed by
+    /// [`Context::with_max_depth`](crate::context::Context::with_max_depth).
+    NestingTooDeep { max_depth: usize },
+
+    /// The parse took more steps than allowed by
+    /// [`Context::with_fuel`](crate::context::Context::with_fuel).
+    OutOfFuel,
+
+    /// More errors were reported than allowed by
+    /// [`Context::with_max_errors`](crate::context::Context::with_max_errors).
+    TooManyErrors { max_errors: usize },
+
+    /// The parser needs more input than has arrived so far.
+    Incomplete,
+
+    /// The input couldn't be read.
+    ReadFailed { message: String },
+
+    /// The input ended partway through a binary value.
+    Truncated { expected: usize, found: usize },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {:?}", self.kind, self.span)
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ZeroWidthRepeat { label } => {
+                write!(f, "repeated parser `{label}` matched empty input")
+            }
+            Self::TooFewRepeats { expected, found } => {
+                write!(f, "expected {expected} repetitions, found {found}")
+            }
+            Self::TrailingInput => write!(f, "unexpected trailing input"),
+            Self::IntegerOutOfRange => write!(f, "integer literal out of range"),
+            Self::InvalidEscape => write!(f, "invalid escape sequence"),
+            Self::ControlCharacter => write!(f, "unescaped control character in string"),
+            Self::UnterminatedString => write!(f, "unterminated string literal"),
+            Self::UnterminatedComment => write!(f, "unterminated block comment"),
+            Self::KeywordAsIdent { keyword } => {
+                write!(f, "keyword `{keyword}` used as identifier")
+            }
+            Self::UnexpectedIndent => write!(f, "unexpected indent"),
+            Self::InconsistentDedent => {
+                write!(f, "dedent doesn't match any outer indentation level")
+            }
+            Self::Expected { expected } => {
+                write!(f, "expected ")?;
+                for (i, pattern) in expected.iter().enumerate() {
+                    match i {
+                        0 => {}
+                        _ if i + 1 == expected.len() => write!(f, " or ")?,
+                        _ => write!(f, ", ")?,
+                    }
+                    write!(f, "`{pattern}`")?;
+                }
+                Ok(())
+            }
+            Self::NestingTooDeep { max_depth } => {
+                write!(f, "nesting too deep (the limit is {max_depth})")
+            }
+            Self::OutOfFuel => write!(f, "parse took too many steps"),
+            Self::TooManyErrors { max_errors } => {
+                write!(f, "too many errors (the limit is {max_errors})")
+            }
+            Self::Incomplete => write!(f, "incomplete input, need more data"),
+            Self::ReadFailed { message } => write!(f, "couldn't read input: {message}"),
+            Self::Truncated { expected, found } => {
+                write!(f, "expected {expected} bytes, found {found}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl From<ParseError> for String {
+    fn from(error: ParseError) -> Self {
+        error.to_string()
+    }
+}
+
+impl From<ParseError> for () {
+    fn from(_: ParseError) -> Self {}
+}
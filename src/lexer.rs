@@ -0,0 +1,180 @@
+//! Turn a string into a list of tokens with papa parsers, to be parsed by
+//! a second stage of parsers as a [`TokenSlice`].
+//!
+//! [`TokenSlice`]: crate::context::tokens::TokenSlice
+
+use crate::context::Context;
+use crate::prelude::*;
+
+/// How a [`Lexer`] chooses between rules that match at the same place.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum MatchPolicy {
+    /// Use the rule that matches the most input, or the first of those if
+    /// several match the same amount.
+    #[default]
+    Longest,
+    /// Use the first rule that matches.
+    First,
+}
+
+/// A lexer, built from a list of rules that each match a token.
+///
+/// At each place in the input, trivia is skipped, then the rules are tried
+/// according to the [`MatchPolicy`]. Rules that match without consuming any
+/// input are ignored. If no rule matches, the next character is turned into
+/// an error token.
+///
+/// If a rule fails with an error, the error is only reported if no other
+/// rule matches.
+///
+/// Each rule is run once at each place. Any user-defined state it changes is
+/// rewound before the next rule is tried, so rules shouldn't rely on state
+/// to carry information between tokens.
+pub struct Lexer<'a, Token, Error> {
+    rules: Vec<Box<dyn Parser<&'a str, Token, Error> + 'a>>,
+    trivia: Option<Box<dyn Parser<&'a str, (), Error> + 'a>>,
+    error_token: Box<dyn Fn(&'a str) -> Token + 'a>,
+    policy: MatchPolicy,
+}
+
+impl<'a, Token, Error> Lexer<'a, Token, Error> {
+    /// Create a lexer with no rules, where `error_token` makes a token from
+    /// an unrecognised character.
+    pub fn new(error_token: impl Fn(&'a str) -> Token + 'a) -> Self {
+        Self {
+            rules: vec![],
+            trivia: None,
+            error_token: Box::new(error_token),
+            policy: MatchPolicy::default(),
+        }
+    }
+
+    /// Add a rule, which makes a token with `f` from the output of `parser`.
+    pub fn rule<Out: 'a>(
+        mut self,
+        parser: impl Parser<&'a str, Out, Error> + 'a,
+        f: impl Fn(Out) -> Token + 'a,
+    ) -> Self
+    where
+        Token: 'a,
+        Error: 'a,
+    {
+        self.rules.push(Box::new(parser.map(f)));
+        self
+    }
+
+    /// Skip anything matched by `parser` between tokens, such as whitespace
+    /// and comments.
+    pub fn trivia<Out: 'a>(mut self, parser: impl Parser<&'a str, Out, Error> + 'a) -> Self
+    where
+        Error: 'a,
+    {
+        self.trivia = Some(Box::new(parser.map(|_| ())));
+        self
+    }
+
+    pub fn policy(mut self, policy: MatchPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Split `input` into tokens.
+    pub fn lex(&self, input: &'a str) -> (Vec<(Token, Span)>, Vec<Error>) {
+        let (tokens, errors) = self.parse_input(input);
+        (tokens.expect("lexer failed"), errors)
+    }
+
+    fn skip_trivia(&self, context: &mut Context<&'a str, Error>) -> ParseResult<(), Error> {
+        if let Some(trivia) = &self.trivia {
+            let result = trivia.parse(context);
+            context.report_result(result);
+            if context.is_exhausted() {
+                return Err(None);
+            }
+        }
+        Ok(())
+    }
+
+    /// Match a token at the current location with the rule chosen by the
+    /// match policy.
+    ///
+    /// Each rule is only run once. The output and errors of the best match
+    /// so far are kept while the other rules are tried.
+    fn token(&self, context: &mut Context<&'a str, Error>) -> ParseResult<Token, Error> {
+        let start = context.location();
+        let errors = context.errors().len();
+        let mut best: Option<(Token, usize, Vec<Error>)> = None;
+        let mut first_error = None;
+
+        for rule in &self.rules {
+            let checkpoint = context.checkpoint();
+            let result = rule.parse(context);
+            let end = context.location();
+            let rule_errors = context.split_off_errors(errors);
+            context.rewind(checkpoint);
+            if context.is_exhausted() {
+                return Err(None);
+            }
+
+            match result {
+                Ok(token) if end > start => {
+                    if best.as_ref().is_none_or(|&(_, best_end, _)| end > best_end) {
+                        best = Some((token, end, rule_errors));
+                    }
+                    if self.policy == MatchPolicy::First {
+                        break;
+                    }
+                }
+                Ok(_) | Err(None) => {}
+                Err(Some(error)) => {
+                    first_error.get_or_insert(error);
+                }
+            }
+        }
+
+        let (token, end, rule_errors) = best.ok_or(first_error)?;
+        context.set_location(end);
+        for error in rule_errors {
+            context.report(error);
+        }
+        if context.is_exhausted() {
+            return Err(None);
+        }
+        Ok(token)
+    }
+}
+
+impl<'a, Token, Error> Parser<&'a str, Vec<(Token, Span)>, Error> for Lexer<'a, Token, Error> {
+    fn parse(
+        &self,
+        context: &mut Context<&'a str, Error>,
+    ) -> ParseResult<Vec<(Token, Span)>, Error> {
+        let mut tokens = vec![];
+
+        loop {
+            self.skip_trivia(context)?;
+            if context.slice_current().is_empty() {
+                return Ok(tokens);
+            }
+
+            let start = context.location();
+            let token = match self.token(context) {
+                Ok(token) => token,
+                // Hitting a limit ends the parse, rather than making an error
+                // token for each char left.
+                Err(_) if context.is_exhausted() => return Err(None),
+                Err(error) => {
+                    if let Some(error) = error {
+                        context.report(error);
+                    }
+                    if context.is_exhausted() {
+                        return Err(None);
+                    }
+                    context.next();
+                    (self.error_token)(&context.slice_all()[start..context.location()])
+                }
+            };
+            tokens.push((token, context.span_from(start)));
+        }
+    }
+}
@@ -1,8 +1,12 @@
 pub mod combinator;
 pub mod context;
+pub mod error;
+pub mod lexer;
 pub mod parser;
 pub mod primitive;
 pub mod recursive;
+#[cfg(feature = "trace")]
+pub mod trace;
 pub mod utils;
 
 #[cfg(test)]
@@ -13,6 +17,10 @@ pub mod prelude {
     pub use crate::combinator::choice::choice;
     pub use crate::context::span::Span;
     pub use crate::parser::{ParseResult, Parser};
-    pub use crate::primitive::{any, func, just, nothing, pred};
+    pub use crate::primitive::{
+        any, end, func, just, just_ignore_case, literal, literal_ignore_case, nothing, pred,
+        select, take_until, take_until_literal, take_while, take_while1, CaseFold,
+    };
     pub use crate::recursive::recursive;
+    pub use crate::select;
 }
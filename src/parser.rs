@@ -1,29 +1,96 @@
 use std::marker::PhantomData;
 
+use crate::combinator::cut::Cut;
 use crate::combinator::drop::Drop;
 use crate::combinator::filter::Filter;
-use crate::combinator::map::Map;
-use crate::combinator::repeat::{NoRepeatOutput, Repeat};
+use crate::combinator::map::{Map, MapExtra, MapWith};
+use crate::combinator::repeat::{NoRepeatOutput, Repeat, RepeatExactly};
 use crate::combinator::spanned::Spanned;
 use crate::combinator::to_slice::ToSlice;
+use crate::combinator::traced::Traced;
 use crate::context::slice::Slice;
-use crate::context::Context;
+use crate::context::{Context, Limits};
+use crate::error::{ErrorKind, ParseError};
+#[cfg(feature = "trace")]
+use crate::trace::Trace;
 
 pub trait Parser<In: Slice, Out, Error> {
     fn parse_input(&self, input: In) -> (Option<Out>, Vec<Error>) {
         let mut context = Context::new(input);
-        let result = self.parse(&mut context);
-        context.result_to_errors(result)
+        let output = self.parse_with(&mut context);
+        (output, context.take_errors())
+    }
+
+    /// Run this parser with an existing context, such as one holding
+    /// user-defined state.
+    ///
+    /// Errors are reported to the context. There's no output if the parse
+    /// was cut short by one of the context's limits.
+    fn parse_with(&self, context: &mut Context<In, Error>) -> Option<Out> {
+        let result = self.parse(context);
+        let output = context.report_result(result);
+        output.filter(|_| !context.is_exhausted())
+    }
+
+    /// Parse some untrusted input, with limits on the resources the parse
+    /// can use.
+    fn parse_with_limits(&self, input: In, limits: Limits) -> (Option<Out>, Vec<Error>)
+    where
+        Error: From<ParseError>,
+    {
+        let mut context = Context::new(input).with_limits(limits);
+        let output = self.parse_with(&mut context);
+        (output, context.take_errors())
+    }
+
+    /// Parse some input, recording a [`Trace`] of the traced parsers and
+    /// [`choice`] branches that were run.
+    ///
+    /// [`choice`]: crate::combinator::choice::choice
+    #[cfg(feature = "trace")]
+    fn parse_traced(&self, input: In) -> (Option<Out>, Vec<Error>, Trace<In>) {
+        let mut context = Context::new(input).with_trace();
+        let output = self.parse_with(&mut context);
+        let trace = context.take_trace().expect("tracing was enabled");
+        (output, context.take_errors(), trace)
+    }
+
+    /// Parse the whole input, reporting an error if there is input left
+    /// over.
+    fn parse_complete(&self, input: In) -> (Option<Out>, Vec<Error>)
+    where
+        Error: From<ParseError>,
+    {
+        let mut context = Context::new(input);
+        let result = self.parse(&mut context).and_then(|output| {
+            if context.slice_current().is_empty() {
+                Ok(output)
+            } else {
+                let span = context.span(context.location(), input.len());
+                Err(Some(ParseError::new(ErrorKind::TrailingInput, span).into()))
+            }
+        });
+        let output = context.report_result(result);
+        (output, context.take_errors())
     }
 
     /// Run this parser.
+    ///
+    /// If the parser fails, the context must be left at the location where
+    /// it started, so that other parsers can be tried from there. All of
+    /// papa's parsers uphold this as long as the parsers they run do.
+    /// Parsers made with [`func`] are rewound when they fail, so the function
+    /// doesn't need to be careful, and other implementations can use
+    /// [`Context::attempt`] to do the same.
+    ///
+    /// [`func`]: crate::primitive::func
     fn parse(&self, context: &mut Context<In, Error>) -> ParseResult<Out, Error>;
 
     /// Map the output of this parser to some other value.
     fn map<F, OutputB>(self, f: F) -> Map<In, Out, OutputB, Error, Self, F>
     where
         Self: Sized,
-        F: Fn(Out) -> OutputB + Copy,
+        F: Fn(Out) -> OutputB,
     {
         Map {
             parser: self,
@@ -32,6 +99,22 @@ pub trait Parser<In: Slice, Out, Error> {
         }
     }
 
+    /// Map the output of this parser to some other value, with access to
+    /// extra information about the match.
+    ///
+    /// See [`MapExtra`] for the information available.
+    fn map_with<F, OutputB>(self, f: F) -> MapWith<In, Out, OutputB, Error, Self, F>
+    where
+        Self: Sized,
+        F: Fn(Out, &mut MapExtra<In, Error>) -> OutputB,
+    {
+        MapWith {
+            parser: self,
+            map: f,
+            _phantom: PhantomData,
+        }
+    }
+
     /// Decide whether to accept an output.
     fn filter<F>(self, f: F) -> Filter<In, Out, Error, Self, F>
     where
@@ -45,6 +128,23 @@ pub trait Parser<In: Slice, Out, Error> {
         }
     }
 
+    /// Commit to this parser: if it fails with an error, an enclosing
+    /// [`choice`] or [`Parser::repeat`] fails with that error too, instead of
+    /// discarding it and trying something else.
+    ///
+    /// Not matching still lets them backtrack as usual.
+    ///
+    /// [`choice`]: crate::combinator::choice::choice
+    fn cut(self) -> Cut<In, Out, Error, Self>
+    where
+        Self: Sized,
+    {
+        Cut {
+            parser: self,
+            _phantom: PhantomData,
+        }
+    }
+
     /// Convert the output of this parser to `()`.
     fn drop(self) -> Drop<In, Out, Error, Self>
     where
@@ -63,10 +163,20 @@ pub trait Parser<In: Slice, Out, Error> {
     where
         Self: Sized,
     {
-        Repeat {
+        Repeat::new(self)
+    }
+
+    /// Repeat this parser exactly `N` times, outputting an array.
+    ///
+    /// Unlike [`Parser::repeat`], this doesn't allocate. Finding fewer than
+    /// `N` matches is reported as an error.
+    fn repeat_exactly<const N: usize>(self) -> RepeatExactly<In, Out, Error, Self, N>
+    where
+        Self: Sized,
+        Error: From<ParseError>,
+    {
+        RepeatExactly {
             parser: self,
-            min: 0,
-            max: None,
             _phantom: PhantomData,
         }
     }
@@ -94,6 +204,24 @@ pub trait Parser<In: Slice, Out, Error> {
             _phantom: PhantomData,
         }
     }
+
+    /// Record an event called `name` each time this parser is run, if
+    /// tracing is enabled with the `trace` feature and
+    /// [`Context::with_trace`].
+    ///
+    /// Without the `trace` feature, this has no effect.
+    ///
+    /// [`Context::with_trace`]: crate::context::Context
+    fn traced(self, name: &'static str) -> Traced<In, Out, Error, Self>
+    where
+        Self: Sized,
+    {
+        Traced {
+            parser: self,
+            name,
+            _phantom: PhantomData,
+        }
+    }
 }
 
 pub type ParseResult<Out, Error> = Result<Out, Option<Error>>;
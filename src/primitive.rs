@@ -1,7 +1,10 @@
 use std::marker::PhantomData;
 
+use memchr::memmem::Finder;
+
 use crate::context::slice::Slice;
 use crate::context::Context;
+use crate::error::{ErrorKind, ParseError};
 use crate::parser::{ParseResult, Parser};
 
 /// Parse any token.
@@ -19,6 +22,7 @@ pub struct Any<In: Slice, Error> {
 impl<In: Slice, Error> Parser<In, In::Token, Error> for Any<In, Error> {
     #[inline]
     fn parse(&self, context: &mut Context<In, Error>) -> ParseResult<In::Token, Error> {
+        context.step()?;
         context.next().ok_or(None)
     }
 }
@@ -36,15 +40,39 @@ pub struct Nothing<In: Slice, Error> {
 }
 
 impl<In: Slice, Error> Parser<In, (), Error> for Nothing<In, Error> {
-    fn parse(&self, _context: &mut Context<In, Error>) -> ParseResult<(), Error> {
+    fn parse(&self, context: &mut Context<In, Error>) -> ParseResult<(), Error> {
+        context.step()?;
         Ok(())
     }
 }
 
+/// Match the end of the input.
+pub fn end<In: Slice, Error>() -> End<In, Error> {
+    End {
+        _phantom: PhantomData,
+    }
+}
+
+#[derive_where::derive_where(Debug, Clone, Copy)]
+pub struct End<In: Slice, Error> {
+    _phantom: PhantomData<*const (In, Error)>,
+}
+
+impl<In: Slice, Error> Parser<In, (), Error> for End<In, Error> {
+    fn parse(&self, context: &mut Context<In, Error>) -> ParseResult<(), Error> {
+        context.step()?;
+        if context.slice_current().is_empty() {
+            Ok(())
+        } else {
+            Err(None)
+        }
+    }
+}
+
 /// Parse a token if it matches a predicate.
 pub fn pred<In: Slice, Error, F>(pred: F) -> Pred<In, Error, F>
 where
-    F: Fn(In::Token) -> bool + Copy,
+    F: Fn(In::Token) -> bool,
 {
     Pred {
         pred,
@@ -52,11 +80,11 @@ where
     }
 }
 
-#[derive_where::derive_where(Debug, Clone, Copy; F)]
+#[derive_where::derive_where(Debug, Clone; F)]
 pub struct Pred<In, Error, F>
 where
     In: Slice,
-    F: Fn(In::Token) -> bool + Copy,
+    F: Fn(In::Token) -> bool,
 {
     pred: F,
     _phantom: PhantomData<*const (In, Error)>,
@@ -65,9 +93,10 @@ where
 impl<In, Error, F> Parser<In, In::Token, Error> for Pred<In, Error, F>
 where
     In: Slice,
-    F: Fn(In::Token) -> bool + Copy,
+    F: Fn(In::Token) -> bool,
 {
     fn parse(&self, context: &mut Context<In, Error>) -> ParseResult<In::Token, Error> {
+        context.step()?;
         let start = context.location();
 
         match context.next() {
@@ -80,6 +109,130 @@ where
     }
 }
 
+/// Parse a token if `f` outputs something for it.
+///
+/// The [`select!`] macro is a more convenient way to build this.
+///
+/// [`select!`]: crate::select!
+pub fn select<In: Slice, Out, Error, F>(f: F) -> Select<In, Out, Error, F>
+where
+    F: Fn(In::Token) -> Option<Out>,
+{
+    Select {
+        select: f,
+        expected: &[],
+        make_error: None,
+        _phantom: PhantomData,
+    }
+}
+
+/// Build a parser that matches a token against some patterns, outputting
+/// the expression for the first pattern that matches.
+///
+/// ```
+/// use papa::error::ParseError;
+/// use papa::prelude::*;
+///
+/// #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// enum Token {
+///     Ident(&'static str),
+///     Int(i64),
+///     Comma,
+/// }
+///
+/// let item = select! {
+///     Token::Ident(name) => name.to_owned(),
+///     Token::Int(n) if n >= 0 => n.to_string(),
+/// };
+///
+/// let tokens = [Token::Int(7), Token::Comma];
+/// let (output, errors): (_, Vec<ParseError>) = item.parse_input(&tokens[..]);
+/// assert_eq!(output.as_deref(), Some("7"));
+/// assert!(errors.is_empty());
+///
+/// let tokens = [Token::Int(-7)];
+/// let (output, errors): (_, Vec<ParseError>) = item.or_error().parse_input(&tokens[..]);
+/// assert_eq!(output, None);
+/// assert_eq!(
+///     errors[0].to_string(),
+///     "expected `Token::Ident(name)` or `Token::Int(n)` at 0..1",
+/// );
+/// ```
+///
+/// The patterns are used as the expected tokens in the error reported by
+/// [`Select::or_error`].
+#[macro_export]
+macro_rules! select {
+    ($($pattern:pat $(if $guard:expr)? => $output:expr),+ $(,)?) => {
+        $crate::primitive::select(move |token| match token {
+            $($pattern $(if $guard)? => ::core::option::Option::Some($output),)+
+            _ => ::core::option::Option::None,
+        })
+        .expected(&[$(::core::stringify!($pattern)),+])
+    };
+}
+
+#[derive_where::derive_where(Debug, Clone; F)]
+pub struct Select<In, Out, Error, F>
+where
+    In: Slice,
+    F: Fn(In::Token) -> Option<Out>,
+{
+    select: F,
+    expected: &'static [&'static str],
+    make_error: Option<fn(ParseError) -> Error>,
+    _phantom: PhantomData<*const (In, Out, Error)>,
+}
+
+impl<In, Out, Error, F> Select<In, Out, Error, F>
+where
+    In: Slice,
+    F: Fn(In::Token) -> Option<Out>,
+{
+    /// Set the descriptions of the tokens this matches, used in the error
+    /// reported by [`Select::or_error`].
+    pub fn expected(mut self, expected: &'static [&'static str]) -> Self {
+        self.expected = expected;
+        self
+    }
+
+    /// Report an error listing the expected tokens if the token doesn't
+    /// match (or there is no token), rather than failing to match.
+    pub fn or_error(mut self) -> Self
+    where
+        Error: From<ParseError>,
+    {
+        self.make_error = Some(Error::from);
+        self
+    }
+}
+
+impl<In, Out, Error, F> Parser<In, Out, Error> for Select<In, Out, Error, F>
+where
+    In: Slice,
+    F: Fn(In::Token) -> Option<Out>,
+{
+    fn parse(&self, context: &mut Context<In, Error>) -> ParseResult<Out, Error> {
+        context.step()?;
+        let start = context.location();
+
+        match context.next().and_then(&self.select) {
+            Some(output) => Ok(output),
+            None => {
+                let error = self.make_error.map(|make_error| {
+                    let span = context.span_from(start);
+                    let kind = ErrorKind::Expected {
+                        expected: self.expected.to_vec(),
+                    };
+                    make_error(ParseError::new(kind, span))
+                });
+                context.set_location(start);
+                Err(error)
+            }
+        }
+    }
+}
+
 /// Match a single token.
 pub fn just<In: Slice, Error>(token: In::Token) -> Just<In, Error>
 where
@@ -105,6 +258,7 @@ where
     In::Token: Eq,
 {
     fn parse(&self, context: &mut Context<In, Error>) -> ParseResult<In::Token, Error> {
+        context.step()?;
         let start = context.location();
 
         match context.next() {
@@ -117,7 +271,180 @@ where
     }
 }
 
+/// Match a single character, ignoring case.
+///
+/// Outputs the character as it appears in the input.
+pub fn just_ignore_case<In, Error>(c: char, fold: CaseFold) -> JustIgnoreCase<In, Error>
+where
+    In: Slice<Token = char>,
+{
+    JustIgnoreCase {
+        c,
+        fold,
+        _phantom: PhantomData,
+    }
+}
+
+#[derive_where::derive_where(Debug, Clone, Copy)]
+pub struct JustIgnoreCase<In, Error>
+where
+    In: Slice<Token = char>,
+{
+    c: char,
+    fold: CaseFold,
+    _phantom: PhantomData<*const (In, Error)>,
+}
+
+impl<In, Error> Parser<In, char, Error> for JustIgnoreCase<In, Error>
+where
+    In: Slice<Token = char>,
+{
+    fn parse(&self, context: &mut Context<In, Error>) -> ParseResult<char, Error> {
+        context.step()?;
+        let start = context.location();
+
+        match context.next() {
+            Some(c) if self.fold.eq(c, self.c) => Ok(c),
+            _ => {
+                context.set_location(start);
+                Err(None)
+            }
+        }
+    }
+}
+
+/// Match a sequence of characters, outputting the matched slice.
+pub fn literal<In, Error>(literal: &'static str) -> Literal<In, Error>
+where
+    In: Slice<Token = char>,
+{
+    Literal {
+        literal,
+        fold: None,
+        _phantom: PhantomData,
+    }
+}
+
+/// Match a sequence of characters, ignoring case.
+///
+/// Outputs the matched slice, as it appears in the input.
+pub fn literal_ignore_case<In, Error>(literal: &'static str, fold: CaseFold) -> Literal<In, Error>
+where
+    In: Slice<Token = char>,
+{
+    Literal {
+        literal,
+        fold: Some(fold),
+        _phantom: PhantomData,
+    }
+}
+
+#[derive_where::derive_where(Debug, Clone, Copy)]
+pub struct Literal<In, Error>
+where
+    In: Slice<Token = char>,
+{
+    literal: &'static str,
+    fold: Option<CaseFold>,
+    _phantom: PhantomData<*const (In, Error)>,
+}
+
+impl<In, Error> Parser<In, In, Error> for Literal<In, Error>
+where
+    In: Slice<Token = char>,
+{
+    fn parse(&self, context: &mut Context<In, Error>) -> ParseResult<In, Error> {
+        context.step()?;
+        let current = context.slice_current();
+        let rest = match self.fold {
+            Some(fold) => fold.strip_literal(current, self.literal),
+            None => strip_literal(current, self.literal),
+        }
+        .ok_or(None)?;
+
+        let len = current.len() - rest.len();
+        context.set_location(context.location() + len);
+        Ok(current.slice(0, len).expect("couldn't get slice"))
+    }
+}
+
+/// Strip `literal` from the start of `input`, if it's there.
+pub(crate) fn strip_literal<In>(mut input: In, literal: &str) -> Option<In>
+where
+    In: Slice<Token = char>,
+{
+    for expected in literal.chars() {
+        match input.next() {
+            Some((c, rest)) if c == expected => input = rest,
+            _ => return None,
+        }
+    }
+    Some(input)
+}
+
+/// How characters are compared when ignoring case.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CaseFold {
+    /// Only ASCII letters are compared without case.
+    Ascii,
+    /// Characters are compared by their Unicode simple case folding, so `ſ`
+    /// matches `s` and `ς` matches `Σ`. Characters that only fold to several
+    /// characters, such as `ß` to `ss`, only match themselves.
+    Unicode,
+}
+
+impl CaseFold {
+    /// Whether two characters are equal, ignoring case.
+    pub fn eq(self, a: char, b: char) -> bool {
+        match self {
+            Self::Ascii => a.eq_ignore_ascii_case(&b),
+            Self::Unicode => a == b || fold_char(a) == fold_char(b),
+        }
+    }
+
+    /// Strip `literal` from the start of `input`, ignoring case.
+    pub(crate) fn strip_literal<In>(self, mut input: In, literal: &str) -> Option<In>
+    where
+        In: Slice<Token = char>,
+    {
+        for expected in literal.chars() {
+            match input.next() {
+                Some((c, rest)) if self.eq(c, expected) => input = rest,
+                _ => return None,
+            }
+        }
+        Some(input)
+    }
+}
+
+/// Fold a character to a canonical case, for [`CaseFold::Unicode`].
+///
+/// Lowercasing alone isn't enough, as some lowercase characters (such as `ς`
+/// and `ſ`) have the same uppercase as another lowercase character, so the
+/// character is lowercased, uppercased and lowercased again. Mappings to more
+/// than one character are ignored, as they aren't simple case foldings.
+fn fold_char(c: char) -> char {
+    fn single(mut chars: impl Iterator<Item = char>) -> Option<char> {
+        let c = chars.next()?;
+        chars.next().is_none().then_some(c)
+    }
+
+    if c.is_ascii() {
+        return c.to_ascii_lowercase();
+    }
+
+    let Some(lower) = single(c.to_lowercase()) else {
+        return c;
+    };
+    single(lower.to_uppercase())
+        .and_then(|upper| single(upper.to_lowercase()))
+        .unwrap_or(lower)
+}
+
 /// Construct a parser from a function.
+///
+/// The context is rewound if the function fails, so it may consume input
+/// or report errors before failing.
 pub fn func<In, Out, Error, F>(f: F) -> FuncParser<In, Out, Error, F>
 where
     In: Slice,
@@ -145,6 +472,166 @@ where
     F: Fn(&mut Context<In, Error>) -> ParseResult<Out, Error>,
 {
     fn parse(&self, context: &mut Context<In, Error>) -> ParseResult<Out, Error> {
-        (self.func)(context)
+        context.step()?;
+        context.attempt(&self.func)
+    }
+}
+
+/// Match the longest (possibly empty) run of tokens that match a predicate,
+/// outputting the matched slice.
+///
+/// Faster than `pred(f).repeat().to_slice()`, as the input is scanned
+/// directly.
+pub fn take_while<In: Slice, Error, F>(pred: F) -> TakeWhile<In, Error, F>
+where
+    F: Fn(In::Token) -> bool,
+{
+    TakeWhile {
+        pred,
+        non_empty: false,
+        _phantom: PhantomData,
+    }
+}
+
+/// Like [`take_while`], but match at least one token.
+pub fn take_while1<In: Slice, Error, F>(pred: F) -> TakeWhile<In, Error, F>
+where
+    F: Fn(In::Token) -> bool,
+{
+    TakeWhile {
+        pred,
+        non_empty: true,
+        _phantom: PhantomData,
+    }
+}
+
+#[derive_where::derive_where(Debug, Clone; F)]
+pub struct TakeWhile<In, Error, F>
+where
+    In: Slice,
+    F: Fn(In::Token) -> bool,
+{
+    pred: F,
+    non_empty: bool,
+    _phantom: PhantomData<*const (In, Error)>,
+}
+
+impl<In, Error, F> Parser<In, In, Error> for TakeWhile<In, Error, F>
+where
+    In: Slice,
+    F: Fn(In::Token) -> bool,
+{
+    fn parse(&self, context: &mut Context<In, Error>) -> ParseResult<In, Error> {
+        context.step()?;
+        let current = context.slice_current();
+        let len = current.prefix_len(&self.pred);
+        if len == 0 && self.non_empty {
+            return Err(None);
+        }
+
+        context.set_location(context.location() + len);
+        Ok(current.slice(0, len).expect("couldn't get slice"))
+    }
+}
+
+/// Match tokens up to (but not including) the first place `terminator`
+/// matches, outputting the matched slice.
+///
+/// Fails if `terminator` never matches, or with the error of `terminator` if
+/// it fails with one.
+pub fn take_until<In, TermOut, Error, P>(terminator: P) -> TakeUntil<In, TermOut, Error, P>
+where
+    In: Slice,
+    P: Parser<In, TermOut, Error>,
+{
+    TakeUntil {
+        terminator,
+        _phantom: PhantomData,
+    }
+}
+
+#[derive_where::derive_where(Debug, Clone; P)]
+pub struct TakeUntil<In, TermOut, Error, P>
+where
+    In: Slice,
+    P: Parser<In, TermOut, Error>,
+{
+    terminator: P,
+    _phantom: PhantomData<*const (In, TermOut, Error)>,
+}
+
+impl<In, TermOut, Error, P> Parser<In, In, Error> for TakeUntil<In, TermOut, Error, P>
+where
+    In: Slice,
+    P: Parser<In, TermOut, Error>,
+{
+    fn parse(&self, context: &mut Context<In, Error>) -> ParseResult<In, Error> {
+        context.step()?;
+        let start = context.location();
+
+        loop {
+            let end = context.location();
+            let checkpoint = context.checkpoint();
+            match context.attempt(|context| self.terminator.parse(context)) {
+                Ok(_) => {
+                    context.rewind(checkpoint);
+                    return Ok(context
+                        .slice_all()
+                        .slice(start, end)
+                        .expect("couldn't get slice"));
+                }
+                Err(Some(error)) => {
+                    context.set_location(start);
+                    return Err(Some(error));
+                }
+                Err(None) => {}
+            }
+
+            if context.is_exhausted() || context.next().is_none() {
+                context.set_location(start);
+                return Err(None);
+            }
+        }
+    }
+}
+
+/// Match bytes up to (but not including) the first occurrence of `literal`,
+/// outputting the matched slice.
+///
+/// The input is searched with [`memchr::memmem`], so this is much faster
+/// than [`take_until`]. Locations in the input must be byte offsets, as they
+/// are for `&str` and `&[u8]`.
+///
+/// Fails if `literal` never occurs.
+pub fn take_until_literal<In, Error>(literal: impl AsRef<[u8]>) -> TakeUntilLiteral<In, Error>
+where
+    In: Slice + AsRef<[u8]>,
+{
+    TakeUntilLiteral {
+        finder: Finder::new(literal.as_ref()).into_owned(),
+        _phantom: PhantomData,
+    }
+}
+
+#[derive_where::derive_where(Debug, Clone)]
+pub struct TakeUntilLiteral<In, Error>
+where
+    In: Slice + AsRef<[u8]>,
+{
+    finder: Finder<'static>,
+    _phantom: PhantomData<*const (In, Error)>,
+}
+
+impl<In, Error> Parser<In, In, Error> for TakeUntilLiteral<In, Error>
+where
+    In: Slice + AsRef<[u8]>,
+{
+    fn parse(&self, context: &mut Context<In, Error>) -> ParseResult<In, Error> {
+        context.step()?;
+        let current = context.slice_current();
+        let len = self.finder.find(current.as_ref()).ok_or(None)?;
+
+        context.set_location(context.location() + len);
+        Ok(current.slice(0, len).expect("couldn't get slice"))
     }
 }
@@ -31,16 +31,34 @@ impl<'a, In: Slice, Out, Error> Parser<In, Out, Error> for Rc<Recursive<'a, In,
             .parser
             .get()
             .expect("parser called before (end of) definition");
-        parser.parse(context)
+        context.nested(|context| grow_stack(|| parser.parse(context)))
     }
 }
 
+/// Run `f`, first growing the stack if it's about to run out.
+#[cfg(feature = "stacker")]
+fn grow_stack<R>(f: impl FnOnce() -> R) -> R {
+    const RED_ZONE: usize = 64 * 1024;
+    const NEW_STACK_SIZE: usize = 1024 * 1024;
+
+    stacker::maybe_grow(RED_ZONE, NEW_STACK_SIZE, f)
+}
+
+#[cfg(not(feature = "stacker"))]
+fn grow_stack<R>(f: impl FnOnce() -> R) -> R {
+    f()
+}
+
 /// Construct a parser that can call itself.
 ///
 /// A reference to the parser itself is provided to the builder, and can be used
 /// to construct a recursive parser.
 ///
 /// Panics if the parser is called inside the builder.
+///
+/// Deeply nested input can overflow the stack. To prevent this, limit the
+/// nesting with [`Context::with_max_depth`], or enable the `stacker` feature
+/// to grow the stack as needed.
 pub fn recursive<'a, In: Slice, Out, Error, P: Parser<In, Out, Error> + 'a>(
     build_parser: impl Fn(Rc<Recursive<'a, In, Out, Error>>) -> P,
 ) -> Rc<Recursive<'a, In, Out, Error>> {
@@ -41,3 +41,1788 @@ fn test_ident_whitespace() {
         ],
     );
 }
+
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic(expected = "matched empty input")]
+fn test_repeat_zero_width_panics() {
+    nothing::<&str, String>().repeat().parse_input("abc");
+}
+
+#[test]
+fn test_repeat_zero_width_bounded() {
+    run_tests(
+        choice((just('a').map(|_| 'a'), nothing().map(|_| '-')))
+            .repeat()
+            .max(3)
+            .collect::<String>(),
+        &[
+            ("", Some("---".to_owned()), vec![]),
+            ("ab", Some("a--".to_owned()), vec![]),
+            ("aaaa", Some("aaa".to_owned()), vec![]),
+        ],
+    );
+}
+
+#[test]
+fn test_repeat_zero_width_stop() {
+    run_tests(
+        nothing().repeat().allow_zero_width().collect::<Vec<_>>(),
+        &[
+            ("", Some(vec![()]), vec![]),
+            ("abc", Some(vec![()]), vec![]),
+        ],
+    );
+}
+
+#[test]
+fn test_repeat_zero_width_error() {
+    run_tests(
+        crate::utils::space()
+            .repeat()
+            .label("space")
+            .zero_width_error(),
+        &[(
+            "abc",
+            None,
+            vec!["repeated parser `space` matched empty input at 0..0".to_owned()],
+        )],
+    );
+}
+
+#[test]
+fn test_cut() {
+    use crate::context::Context;
+
+    let fail = || {
+        func(|context: &mut Context<&str, String>| {
+            just('!').parse(context)?;
+            Err(Some(format!("error at {}", context.location())))
+        })
+    };
+
+    // Without a cut, errors are discarded when backtracking.
+    run_tests(
+        choice((fail(), crate::primitive::any())),
+        &[("!", Some('!'), vec![])],
+    );
+    run_tests(
+        choice((fail().cut(), crate::primitive::any())),
+        &[
+            ("a", Some('a'), vec![]),
+            ("!", None, vec!["error at 1".to_owned()]),
+        ],
+    );
+    run_tests(
+        choice((choice((just('a'), fail().cut())), crate::primitive::any())),
+        &[("!", None, vec!["error at 1".to_owned()])],
+    );
+    run_tests(
+        choice((just('a'), fail()))
+            .repeat()
+            .min(1)
+            .collect::<String>(),
+        &[("aa!", Some("aa".to_owned()), vec![])],
+    );
+    run_tests(
+        choice((just('a'), fail().cut()))
+            .repeat()
+            .min(1)
+            .collect::<String>(),
+        &[
+            ("aab", Some("aa".to_owned()), vec![]),
+            ("aa!", None, vec!["error at 3".to_owned()]),
+        ],
+    );
+}
+
+#[test]
+fn test_repeat_exactly() {
+    run_tests(
+        pred(|c: char| c.is_ascii_hexdigit())
+            .repeat()
+            .exactly(4)
+            .collect::<String>(),
+        &[
+            ("12ab", Some("12ab".to_owned()), vec![]),
+            ("12abc", Some("12ab".to_owned()), vec![]),
+            (
+                "12",
+                None,
+                vec!["expected 4 repetitions, found 2 at 0..2".to_owned()],
+            ),
+        ],
+    );
+
+    run_tests(
+        pred(|c: char| c.is_ascii_hexdigit())
+            .repeat()
+            .exactly(4)
+            .min(2)
+            .collect::<String>(),
+        &[("12", Some("12".to_owned()), vec![])],
+    );
+}
+
+#[test]
+fn test_repeat_exactly_array() {
+    run_tests(
+        pred(|c: char| c.is_ascii_hexdigit()).repeat_exactly::<4>(),
+        &[
+            ("12ab", Some(['1', '2', 'a', 'b']), vec![]),
+            (
+                "12",
+                None,
+                vec!["expected 4 repetitions, found 2 at 0..2".to_owned()],
+            ),
+        ],
+    );
+}
+
+#[test]
+fn test_take_while() {
+    run_tests(
+        take_while(|c: char| c.is_alphabetic()),
+        &[
+            ("", Some(""), vec![]),
+            ("héllo world", Some("héllo"), vec![]),
+            ("日本語→", Some("日本語"), vec![]),
+            ("123", Some(""), vec![]),
+        ],
+    );
+    run_tests(
+        take_while1(|c: char| c.is_alphabetic()),
+        &[("", None, vec![]), ("héllo world", Some("héllo"), vec![])],
+    );
+}
+
+#[test]
+fn test_take_until() {
+    use crate::context::{Context, Limits};
+
+    run_tests(
+        take_until(just('\n')),
+        &[
+            ("", None, vec![]),
+            ("no newline", None, vec![]),
+            ("first\nsecond", Some("first"), vec![]),
+        ],
+    );
+
+    let newline_or_bang =
+        func(
+            |context: &mut Context<&str, String>| match any().parse(context)? {
+                '\n' => Ok(()),
+                '!' => Err(Some(format!("error at {}", context.location() - 1))),
+                _ => Err(None),
+            },
+        );
+    run_tests(
+        take_until(newline_or_bang),
+        &[
+            ("first\nsecond!", Some("first"), vec![]),
+            ("first!\nsecond", None, vec!["error at 5".to_owned()]),
+        ],
+    );
+
+    let (output, errors): (_, Vec<String>) = take_until(just('\n')).parse_with_limits(
+        "no newline",
+        Limits {
+            fuel: Some(5),
+            ..Limits::default()
+        },
+    );
+    assert_eq!(output, None);
+    assert_eq!(errors, vec!["parse took too many steps at 4..4".to_owned()]);
+}
+
+#[test]
+fn test_take_until_literal() {
+    run_tests(
+        chain((take_until_literal("*/"), any(), any())).map(|(body, _, _)| body),
+        &[
+            ("comment */ rest", Some("comment "), vec![]),
+            ("ünïcödé*/", Some("ünïcödé"), vec![]),
+            ("unterminated", None, vec![]),
+        ],
+    );
+}
+
+#[test]
+fn test_end() {
+    run_tests(
+        chain((crate::utils::ident(), end())).map(|(ident, _)| ident),
+        &[
+            ("", None, vec![]),
+            ("hello", Some("hello"), vec![]),
+            ("hello world", None, vec![]),
+        ],
+    );
+}
+
+#[test]
+fn test_parse_complete() {
+    let parser = crate::utils::ident::<&str, String>();
+    assert_eq!(parser.parse_complete("hello"), (Some("hello"), vec![]));
+    assert_eq!(
+        parser.parse_complete("hello world"),
+        (None, vec!["unexpected trailing input at 5..11".to_owned()])
+    );
+}
+
+#[test]
+fn test_filter_rewinds() {
+    run_tests(
+        chain((
+            choice((
+                crate::utils::ident().filter(|&ident| ident == "let"),
+                take_while(|c: char| c.is_ascii_alphabetic()),
+            )),
+            end(),
+        ))
+        .map(|(ident, _)| ident),
+        &[("let", Some("let"), vec![]), ("var", Some("var"), vec![])],
+    );
+}
+
+#[test]
+fn test_drop_fails() {
+    run_tests(
+        just('a').drop(),
+        &[("a", Some(()), vec![]), ("b", None, vec![])],
+    );
+}
+
+mod backtracking {
+    use proptest::prelude::*;
+
+    use crate::context::Context;
+    use crate::prelude::*;
+
+    /// A grammar for randomly composed parsers, all outputting `()`.
+    #[derive(Debug, Clone)]
+    enum Grammar {
+        Any,
+        Nothing,
+        End,
+        Just(char),
+        TakeWhile(char),
+        TakeUntil(char),
+        /// Consumes some tokens and then fails, violating the contract, so
+        /// it's wrapped in `func` to enforce it.
+        Bad(usize),
+
+        Chain(Box<Grammar>, Box<Grammar>),
+        Choice(Box<Grammar>, Box<Grammar>),
+        Filter(Box<Grammar>, bool),
+        Drop(Box<Grammar>),
+        Spanned(Box<Grammar>),
+        ToSlice(Box<Grammar>),
+        Repeat(Box<Grammar>, usize, Option<usize>),
+        RepeatExactly(Box<Grammar>),
+        Func(Box<Grammar>),
+    }
+
+    struct Boxed<'a>(Box<dyn Parser<&'a str, (), String> + 'a>);
+
+    impl<'a> Parser<&'a str, (), String> for Boxed<'a> {
+        fn parse(&self, context: &mut Context<&'a str, String>) -> ParseResult<(), String> {
+            self.0.parse(context)
+        }
+    }
+
+    struct Bad(usize);
+
+    impl<'a> Parser<&'a str, (), String> for Bad {
+        fn parse(&self, context: &mut Context<&'a str, String>) -> ParseResult<(), String> {
+            for _ in 0..self.0 {
+                context.next();
+            }
+            Err(None)
+        }
+    }
+
+    fn boxed<'a>(parser: impl Parser<&'a str, (), String> + 'a) -> Boxed<'a> {
+        Boxed(Box::new(parser))
+    }
+
+    fn build<'a>(grammar: &Grammar) -> Boxed<'a> {
+        match grammar {
+            Grammar::Any => boxed(crate::primitive::any().map(|_| ())),
+            Grammar::Nothing => boxed(nothing()),
+            Grammar::End => boxed(end()),
+            Grammar::Just(c) => boxed(just(*c).map(|_| ())),
+            Grammar::TakeWhile(c) => {
+                let c = *c;
+                boxed(take_while(move |t| t == c).map(|_| ()))
+            }
+            Grammar::TakeUntil(c) => boxed(take_until(just(*c)).map(|_| ())),
+            Grammar::Bad(n) => {
+                let bad = Bad(*n);
+                boxed(func(move |context| bad.parse(context)))
+            }
+
+            Grammar::Chain(a, b) => boxed(chain((build(a), build(b))).map(|_| ())),
+            Grammar::Choice(a, b) => boxed(choice((build(a), build(b)))),
+            Grammar::Filter(a, accept) => {
+                let accept = *accept;
+                boxed(build(a).filter(move |_| accept))
+            }
+            Grammar::Drop(a) => boxed(build(a).drop()),
+            Grammar::Spanned(a) => boxed(build(a).spanned().map(|_| ())),
+            Grammar::ToSlice(a) => boxed(build(a).to_slice().map(|_| ())),
+            Grammar::Repeat(a, min, max) => {
+                let repeat = build(a).repeat().allow_zero_width().min(*min);
+                match max {
+                    Some(max) => boxed(repeat.max(*max).map(|_| ())),
+                    None => boxed(repeat.map(|_| ())),
+                }
+            }
+            Grammar::RepeatExactly(a) => boxed(build(a).repeat_exactly::<2>().map(|_| ())),
+            Grammar::Func(a) => {
+                let a = build(a);
+                boxed(func(move |context| a.parse(context)))
+            }
+        }
+    }
+
+    fn token() -> impl Strategy<Value = char> {
+        prop_oneof![Just('a'), Just('b'), Just('c')]
+    }
+
+    fn grammar() -> impl Strategy<Value = Grammar> {
+        let leaf = prop_oneof![
+            Just(Grammar::Any),
+            Just(Grammar::Nothing),
+            Just(Grammar::End),
+            token().prop_map(Grammar::Just),
+            token().prop_map(Grammar::TakeWhile),
+            token().prop_map(Grammar::TakeUntil),
+            (1..3usize).prop_map(Grammar::Bad),
+        ];
+
+        leaf.prop_recursive(4, 32, 2, |inner| {
+            prop_oneof![
+                (inner.clone(), inner.clone())
+                    .prop_map(|(a, b)| Grammar::Chain(Box::new(a), Box::new(b))),
+                (inner.clone(), inner.clone())
+                    .prop_map(|(a, b)| Grammar::Choice(Box::new(a), Box::new(b))),
+                (inner.clone(), proptest::bool::ANY)
+                    .prop_map(|(a, accept)| Grammar::Filter(Box::new(a), accept)),
+                inner.clone().prop_map(|a| Grammar::Drop(Box::new(a))),
+                inner.clone().prop_map(|a| Grammar::Spanned(Box::new(a))),
+                inner.clone().prop_map(|a| Grammar::ToSlice(Box::new(a))),
+                (inner.clone(), 0..3usize, proptest::option::of(0..3usize))
+                    .prop_map(|(a, min, max)| Grammar::Repeat(Box::new(a), min, max)),
+                inner
+                    .clone()
+                    .prop_map(|a| Grammar::RepeatExactly(Box::new(a))),
+                inner.prop_map(|a| Grammar::Func(Box::new(a))),
+            ]
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn failed_parsers_rewind(grammar in grammar(), input in "[abc]{0,8}", start in 0..8usize) {
+            let start = start.min(input.len());
+            let parser = build(&grammar);
+
+            let mut context = Context::new(input.as_str());
+            context.set_location(start);
+
+            if parser.parse(&mut context).is_err() {
+                prop_assert_eq!(context.location(), start);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_map_captures() {
+    let keyword = String::from("let");
+    run_tests(
+        crate::utils::ident().map(move |ident| ident == keyword),
+        &[("let", Some(true), vec![]), ("x", Some(false), vec![])],
+    );
+
+    let digits: Vec<char> = "01".chars().collect();
+    run_tests(
+        pred(move |c| digits.contains(&c)),
+        &[("1", Some('1'), vec![]), ("2", None, vec![])],
+    );
+}
+
+#[test]
+fn test_map_with() {
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+
+    let interner = RefCell::new(HashMap::new());
+    let parser = chain((
+        crate::utils::space(),
+        crate::utils::ident().map_with(|_, extra| {
+            let mut interner = interner.borrow_mut();
+            let next_id = interner.len();
+            let id = *interner.entry(extra.slice()).or_insert(next_id);
+            (id, extra.span())
+        }),
+    ))
+    .map(|(_, ident)| ident)
+    .repeat()
+    .collect::<Vec<_>>();
+
+    run_tests(
+        parser,
+        &[(
+            "a b a",
+            Some(vec![
+                (0, Span::new(0, 1)),
+                (1, Span::new(2, 3)),
+                (0, Span::new(4, 5)),
+            ]),
+            vec![],
+        )],
+    );
+}
+
+#[test]
+fn test_state_rewinds() {
+    use crate::context::state::State;
+    use crate::context::Context;
+
+    struct Count(usize);
+
+    impl State for Count {
+        type Checkpoint = usize;
+
+        fn checkpoint(&self) -> usize {
+            self.0
+        }
+
+        fn rewind(&mut self, checkpoint: usize) {
+            self.0 = checkpoint;
+        }
+    }
+
+    let count = just('a').map_with(|_, extra| extra.state_mut::<Count>().unwrap().0 += 1);
+    let parser = choice((
+        chain((count.clone(), just('b'))).drop(),
+        chain((count, just('c'))).drop(),
+        func(|context: &mut Context<&str, String>| {
+            context.state_mut::<Count>().unwrap().0 += 10;
+            Err(None)
+        }),
+    ));
+
+    for (input, output, expected) in [("ab", Some(()), 1), ("ac", Some(()), 1), ("ad", None, 0)] {
+        let mut context = Context::new(input).with_state(Count(0));
+        assert_eq!(parser.parse_with(&mut context), output);
+        assert_eq!(context.take_state::<Count>().unwrap().0, expected);
+    }
+}
+
+#[test]
+fn test_state_checkpoints_are_lazy() {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use crate::context::state::State;
+    use crate::context::Context;
+
+    struct Counted(Rc<Cell<usize>>);
+
+    impl State for Counted {
+        type Checkpoint = ();
+
+        fn checkpoint(&self) {
+            self.0.set(self.0.get() + 1);
+        }
+
+        fn rewind(&mut self, _: ()) {}
+    }
+
+    let checkpoints = Rc::new(Cell::new(0));
+    let letters = choice((just('a'), just('b'))).repeat();
+    let input = "abab".repeat(100);
+    let mut context =
+        Context::<_, String>::new(input.as_str()).with_state(Counted(checkpoints.clone()));
+    letters.parse_with(&mut context);
+    assert_eq!(checkpoints.get(), 1);
+
+    context.set_location(0);
+    let touch = just('a').map_with(|_, extra| {
+        extra.state_mut::<Counted>();
+    });
+    chain((touch, choice((just('b'),)), choice((just('a'),)))).parse_with(&mut context);
+    assert_eq!(checkpoints.get(), 2);
+}
+
+#[test]
+fn test_rewind_discards_errors() {
+    use crate::context::Context;
+
+    let report = func(|context: &mut Context<&str, String>| {
+        just('a').parse(context)?;
+        context.report("reported".to_owned());
+        just('b').parse(context)
+    });
+    run_tests(
+        choice((report, just('a'))),
+        &[
+            ("ab", Some('b'), vec!["reported".to_owned()]),
+            ("ac", Some('a'), vec![]),
+        ],
+    );
+}
+
+#[test]
+fn test_indented_block() {
+    use crate::utils::indent::indented_block;
+
+    #[derive(Debug, PartialEq, Eq)]
+    struct Node<'a>(&'a str, Vec<Node<'a>>);
+
+    let node = || {
+        recursive(|node| {
+            chain((
+                crate::utils::ident(),
+                choice((
+                    chain((just(':'), indented_block(node))).map(|(_, block)| block),
+                    nothing().map(|_| vec![]),
+                )),
+            ))
+            .map(|(name, children)| Node(name, children))
+        })
+    };
+
+    run_tests(
+        node(),
+        &[
+            (
+                "a:\n  b\n\n  c:\n    d\n  e\nf",
+                Some(Node(
+                    "a",
+                    vec![
+                        Node("b", vec![]),
+                        Node("c", vec![Node("d", vec![])]),
+                        Node("e", vec![]),
+                    ],
+                )),
+                vec![],
+            ),
+            ("a:\nb", Some(Node("a", vec![])), vec![]),
+        ],
+    );
+
+    // An error in a block is discarded when `choice` backtracks out of it,
+    // so the outer block is matched directly here.
+    run_tests(
+        chain((crate::utils::ident(), just(':'), indented_block(node())))
+            .map(|(name, _, children)| Node(name, children)),
+        &[
+            (
+                "a:\n    b\n  c",
+                None,
+                vec!["dedent doesn't match any outer indentation level at 8..11".to_owned()],
+            ),
+            (
+                "a:\n  b\n    c",
+                None,
+                vec!["unexpected indent at 6..11".to_owned()],
+            ),
+        ],
+    );
+
+    // Cutting the block makes its errors fail the enclosing `choice`.
+    let node = recursive(|node| {
+        chain((
+            crate::utils::ident(),
+            choice((
+                chain((just(':'), indented_block(node).cut())).map(|(_, block)| block),
+                nothing().map(|_| vec![]),
+            )),
+        ))
+        .map(|(name, children)| Node(name, children))
+    });
+    run_tests(
+        node,
+        &[(
+            "a:\n  b:\n      c\n    d",
+            None,
+            vec!["dedent doesn't match any outer indentation level at 15..20".to_owned()],
+        )],
+    );
+}
+
+#[test]
+fn test_join_lines() {
+    use crate::utils::indent::{join_lines, space};
+
+    let list = || {
+        chain((
+            just('['),
+            chain((space(), crate::utils::ident(), space()))
+                .map(|(_, ident, _)| ident)
+                .repeat()
+                .collect::<Vec<_>>(),
+            just(']'),
+        ))
+        .map(|(_, idents, _)| idents)
+    };
+
+    run_tests(
+        join_lines(list()),
+        &[("[a\n  b\n]", Some(vec!["a", "b"]), vec![])],
+    );
+    run_tests(list(), &[("[a\n  b\n]", None, vec![])]);
+}
+
+#[test]
+fn test_int() {
+    use crate::utils::{int, uint};
+
+    run_tests(
+        int::<_, _, i8>(10),
+        &[
+            ("-128", Some(-128), vec![]),
+            ("+1_2_7", Some(127), vec![]),
+            ("_1", None, vec![]),
+            (
+                "128",
+                None,
+                vec!["integer literal out of range at 0..3".to_owned()],
+            ),
+        ],
+    );
+    run_tests(
+        uint::<_, _, u16>(16),
+        &[("fF_fFg", Some(0xffff), vec![]), ("-1", None, vec![])],
+    );
+    run_tests(int::<_, _, u8>(2), &[("-1", None, vec![])]);
+}
+
+#[test]
+#[should_panic(expected = "radix must be between 2 and 36, got 37")]
+fn test_int_radix_too_large() {
+    crate::utils::int::<&str, String, u32>(37);
+}
+
+#[test]
+fn test_float() {
+    let cases = [
+        "0",
+        "-1.5",
+        "1_000.25",
+        "6.02e23",
+        "1E-7",
+        "+0.1",
+        "1e400",
+        "2.2250738585072011e-308",
+    ];
+    for input in cases {
+        let expected: f64 = input.replace('_', "").parse().unwrap();
+        run_tests(
+            crate::utils::float::<_, _, f64>().map(f64::to_bits),
+            &[(input, Some(expected.to_bits()), vec![])],
+        );
+    }
+
+    run_tests(
+        chain((crate::utils::float::<_, _, f64>(), any())).map(|(_, rest)| rest),
+        &[("1.e", Some('.'), vec![]), ("1e+x", Some('e'), vec![])],
+    );
+}
+
+#[test]
+fn test_number() {
+    use crate::utils::{number, Number};
+
+    let parser = number::<_, _, i32, f64>().map(|n| match n {
+        Number::Int(i) => Number::Int(i),
+        Number::Float(f) => Number::Float(f.to_bits()),
+    });
+    run_tests(
+        parser,
+        &[
+            ("42", Some(Number::Int(42)), vec![]),
+            (
+                "-0x7fff_ffff",
+                Some(Number::Int(i32::MAX.wrapping_neg())),
+                vec![],
+            ),
+            ("0o17", Some(Number::Int(0o17)), vec![]),
+            ("0b1010", Some(Number::Int(10)), vec![]),
+            ("0x", Some(Number::Int(0)), vec![]),
+            ("-2.5e3", Some(Number::Float((-2.5e3f64).to_bits())), vec![]),
+            (
+                "0x1_0000_0000",
+                None,
+                vec!["integer literal out of range at 0..13".to_owned()],
+            ),
+        ],
+    );
+}
+
+#[test]
+fn test_string_literal() {
+    use std::borrow::Cow;
+
+    use crate::utils::{string_literal, StringConfig};
+
+    run_tests(
+        string_literal(StringConfig::json()).map(|s| matches!(s, Cow::Borrowed(_))),
+        &[
+            (r#""plain""#, Some(true), vec![]),
+            (r#""esc\"aped""#, Some(false), vec![]),
+        ],
+    );
+
+    run_tests(
+        string_literal(StringConfig::json()),
+        &[
+            (r#""a\né😀\/""#, Some("a\né😀/".into()), vec![]),
+            (
+                r#""a\qb\ud83dc""#,
+                Some("abc".into()),
+                vec![
+                    "invalid escape sequence at 2..4".to_owned(),
+                    "invalid escape sequence at 5..11".to_owned(),
+                ],
+            ),
+            (
+                "\"a\tb\"",
+                Some("a\tb".into()),
+                vec!["unescaped control character in string at 2..3".to_owned()],
+            ),
+            ("\"a\u{7f}\u{85}b\"", Some("a\u{7f}\u{85}b".into()), vec![]),
+            (
+                r#""abc\""#,
+                None,
+                vec!["unterminated string literal at 0..1".to_owned()],
+            ),
+            ("abc", None, vec![]),
+        ],
+    );
+
+    run_tests(
+        string_literal(StringConfig::rust()),
+        &[
+            (r#""\u{1F600}\0""#, Some("😀\0".into()), vec![]),
+            (
+                r##"r#"raw "\n" string"#"##,
+                Some(r#"raw "\n" string"#.into()),
+                vec![],
+            ),
+            ("r\"multi\nline\"", Some("multi\nline".into()), vec![]),
+            (
+                r#""\u{110000}""#,
+                Some("".into()),
+                vec!["invalid escape sequence at 1..11".to_owned()],
+            ),
+            (
+                r#""a\u{zz}b\u{1234567}c""#,
+                Some("abc".into()),
+                vec![
+                    "invalid escape sequence at 2..8".to_owned(),
+                    "invalid escape sequence at 9..20".to_owned(),
+                ],
+            ),
+            (
+                r#""a\u{12""#,
+                Some("a".into()),
+                vec!["invalid escape sequence at 2..7".to_owned()],
+            ),
+        ],
+    );
+
+    // Errors reported inside a string are discarded if it's backtracked out
+    // of.
+    run_tests(
+        choice((
+            chain((string_literal(StringConfig::json()), just(':'))).map(|(s, _)| s),
+            crate::primitive::any().repeat().map(|_| "raw".into()),
+        )),
+        &[
+            (
+                r#""a\qb":"#,
+                Some("ab".into()),
+                vec!["invalid escape sequence at 2..4".to_owned()],
+            ),
+            (r#""a\qb""#, Some("raw".into()), vec![]),
+        ],
+    );
+}
+
+#[test]
+fn test_literal() {
+    run_tests(
+        literal("->"),
+        &[("->x", Some("->"), vec![]), ("-x", None, vec![])],
+    );
+}
+
+#[test]
+fn test_trivia() {
+    use crate::utils::{trivia, CommentKind, TriviaConfig};
+
+    let config = TriviaConfig::c().line_comment("#").collect(true);
+    run_tests(
+        chain((trivia(config), crate::utils::ident())).map(|(comments, ident)| {
+            let comments: Vec<_> = comments
+                .iter()
+                .map(|comment| (comment.kind(), comment.span(), comment.text()))
+                .collect();
+            (comments, ident)
+        }),
+        &[
+            ("x", Some((vec![], "x")), vec![]),
+            (
+                " // one\n# two\n /* three */x",
+                Some((
+                    vec![
+                        (CommentKind::Line, Span::new(1, 7), "// one"),
+                        (CommentKind::Line, Span::new(8, 13), "# two"),
+                        (CommentKind::Block, Span::new(15, 26), "/* three */"),
+                    ],
+                    "x",
+                )),
+                vec![],
+            ),
+            ("x /* a", Some((vec![], "x")), vec![]),
+            (
+                "/* a",
+                None,
+                vec!["unterminated block comment at 0..2".to_owned()],
+            ),
+        ],
+    );
+
+    let nested = TriviaConfig::c().nested(true);
+    run_tests(
+        chain((trivia(nested), crate::utils::ident())).map(|(_, ident)| ident),
+        &[
+            ("/* a /* b */ c */ x", Some("x"), vec![]),
+            (
+                "/* a /* b */ x",
+                None,
+                vec!["unterminated block comment at 0..2".to_owned()],
+            ),
+        ],
+    );
+}
+
+#[test]
+fn test_ident_unicode() {
+    use crate::utils::ident;
+
+    run_tests(
+        ident(),
+        &[
+            ("naïve x", Some("naïve"), vec![]),
+            ("变量=1", Some("变量"), vec![]),
+            ("_1", Some("_1"), vec![]),
+            ("1a", None, vec![]),
+            ("\u{301}a", None, vec![]),
+        ],
+    );
+    run_tests(ident().ascii(), &[("naïve", Some("na"), vec![])]);
+}
+
+#[test]
+fn test_ident_rules() {
+    use crate::utils::ident;
+
+    run_tests(
+        ident().medial_chars("-"),
+        &[
+            ("font-size: 1", Some("font-size"), vec![]),
+            ("a--b", Some("a"), vec![]),
+            ("a- b", Some("a"), vec![]),
+            ("-a", None, vec![]),
+        ],
+    );
+    run_tests(
+        ident().extra_chars("$"),
+        &[
+            ("$el", Some("$el"), vec![]),
+            ("a$b", Some("a$b"), vec![]),
+            ("_x", Some("_x"), vec![]),
+        ],
+    );
+    run_tests(
+        ident().start_chars("@"),
+        &[
+            ("@attr_1", Some("@attr_1"), vec![]),
+            ("a@b", Some("a"), vec![]),
+        ],
+    );
+}
+
+#[test]
+fn test_keywords() {
+    use std::rc::Rc;
+
+    use crate::utils::{ident, Keywords};
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Kw {
+        If,
+        In,
+        Int,
+    }
+
+    let keywords = Rc::new(Keywords::new([
+        ("if", Kw::If),
+        ("in", Kw::In),
+        ("int", Kw::Int),
+    ]));
+    assert_eq!(keywords.get("in"), Some(&Kw::In));
+    assert_eq!(keywords.get("i"), None);
+
+    run_tests(
+        ident().keyword(keywords.clone()),
+        &[
+            ("if x", Some(Kw::If), vec![]),
+            ("int", Some(Kw::Int), vec![]),
+            ("in(", Some(Kw::In), vec![]),
+            ("integer", None, vec![]),
+            ("i", None, vec![]),
+        ],
+    );
+
+    run_tests(
+        ident().excluding(keywords.clone()).or_error(),
+        &[
+            ("integer", Some("integer"), vec![]),
+            ("i", Some("i"), vec![]),
+            (
+                "int x",
+                None,
+                vec!["keyword `int` used as identifier at 0..3".to_owned()],
+            ),
+        ],
+    );
+    run_tests(
+        choice((ident().excluding(keywords.clone()), literal("int"))),
+        &[("int", Some("int"), vec![]), ("x", Some("x"), vec![])],
+    );
+    run_tests(
+        chain((ident().excluding(keywords.clone()), just(' ')))
+            .map(|(ident, _)| ident)
+            .repeat()
+            .collect::<Vec<_>>(),
+        &[("a b int ", Some(vec!["a", "b"]), vec![])],
+    );
+
+    // The table is owned, so the parser can be returned from a function.
+    fn if_keyword<'a>() -> impl Parser<&'a str, Kw, String> {
+        ident().keyword(Keywords::new([("if", Kw::If)]))
+    }
+    run_tests(
+        if_keyword(),
+        &[("if", Some(Kw::If), vec![]), ("in", None, vec![])],
+    );
+}
+
+#[test]
+fn test_ignore_case() {
+    use crate::utils::kwd_ignore_case;
+
+    run_tests(
+        literal_ignore_case("select", CaseFold::Ascii),
+        &[
+            ("SELECT *", Some("SELECT"), vec![]),
+            ("Select", Some("Select"), vec![]),
+            ("selec", None, vec![]),
+        ],
+    );
+
+    run_tests(
+        just_ignore_case('é', CaseFold::Ascii),
+        &[("é", Some('é'), vec![]), ("É", None, vec![])],
+    );
+
+    run_tests(
+        just_ignore_case('é', CaseFold::Unicode),
+        &[("É", Some('É'), vec![]), ("e", None, vec![])],
+    );
+
+    run_tests(
+        literal_ignore_case("σς", CaseFold::Unicode),
+        &[("ΣΣ", Some("ΣΣ"), vec![]), ("σσ", Some("σσ"), vec![])],
+    );
+
+    run_tests(
+        literal_ignore_case("ſ", CaseFold::Unicode),
+        &[("S", Some("S"), vec![]), ("ss", Some("s"), vec![])],
+    );
+
+    run_tests(
+        kwd_ignore_case("from", CaseFold::Ascii),
+        &[
+            ("FROM t", Some("FROM"), vec![]),
+            ("fromage", None, vec![]),
+            ("fro", None, vec![]),
+        ],
+    );
+}
+
+#[test]
+fn test_binary() {
+    use crate::utils::binary::{
+        be_f32, be_i64, be_u16, le_u32, length_prefixed, sleb128, take, uleb128,
+    };
+
+    fn parse<Out>(
+        parser: impl Parser<&'static [u8], Out, String>,
+        input: &'static [u8],
+    ) -> (Option<Out>, Vec<String>) {
+        parser.parse_input(input)
+    }
+
+    assert_eq!(parse(be_u16(), &[0x12, 0x34, 0x56]), (Some(0x1234), vec![]));
+    assert_eq!(
+        parse(le_u32(), &[0x78, 0x56, 0x34, 0x12]),
+        (Some(0x12345678), vec![])
+    );
+    assert_eq!(parse(be_i64(), &[0xff; 8]), (Some(-1), vec![]));
+    assert_eq!(parse(be_f32(), &[0x3f, 0x80, 0, 0]), (Some(1.0), vec![]));
+    assert_eq!(parse(be_u16(), &[]), (None, vec![]));
+    assert_eq!(
+        parse(le_u32(), &[1, 2]),
+        (None, vec!["expected 4 bytes, found 2 at 0..2".to_owned()])
+    );
+    assert_eq!(
+        parse(be_u16().repeat().collect(), &[0, 1, 0, 2]),
+        (Some(vec![1, 2]), vec![])
+    );
+
+    assert_eq!(parse(take(2), b"abc"), (Some(&b"ab"[..]), vec![]));
+    assert_eq!(
+        parse(take(4), b"abc"),
+        (None, vec!["expected 4 bytes, found 3 at 0..3".to_owned()])
+    );
+
+    assert_eq!(
+        parse(uleb128(), &[0xe5, 0x8e, 0x26]),
+        (Some(624485), vec![])
+    );
+    assert_eq!(parse(uleb128(), &[0x80, 0x80, 0x00]), (Some(0), vec![]));
+    assert_eq!(
+        parse(
+            uleb128(),
+            &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01]
+        ),
+        (Some(u64::MAX), vec![])
+    );
+    assert_eq!(
+        parse(
+            uleb128(),
+            &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x02]
+        ),
+        (
+            None,
+            vec!["integer literal out of range at 0..10".to_owned()]
+        )
+    );
+    assert_eq!(
+        parse(uleb128(), &[0x80, 0x80]),
+        (None, vec!["expected 3 bytes, found 2 at 0..2".to_owned()])
+    );
+    assert_eq!(
+        parse(sleb128(), &[0xc0, 0xbb, 0x78]),
+        (Some(-123456), vec![])
+    );
+    assert_eq!(parse(sleb128(), &[0x3f]), (Some(63), vec![]));
+    assert_eq!(parse(sleb128(), &[0x40]), (Some(-64), vec![]));
+    assert_eq!(
+        parse(
+            sleb128(),
+            &[0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x7f]
+        ),
+        (Some(i64::MIN), vec![])
+    );
+
+    let frame = length_prefixed(be_u16(), take(3));
+    assert_eq!(
+        parse(frame, &[0, 3, b'a', b'b', b'c', b'd']),
+        (Some(&b"abc"[..]), vec![])
+    );
+    let frame = length_prefixed(be_u16(), chain((be_u16(), take(3))));
+    assert_eq!(
+        parse(frame, &[0, 4, 0, 1, b'a', b'b']),
+        (None, vec!["expected 3 bytes, found 2 at 4..6".to_owned()])
+    );
+    let frame = length_prefixed(be_u16(), take(1));
+    assert_eq!(
+        parse(frame, &[0, 2, b'a', b'b']),
+        (None, vec!["unexpected trailing input at 3..4".to_owned()])
+    );
+    let frame = length_prefixed(be_u16(), take(1));
+    assert_eq!(
+        parse(frame, &[0, 5, b'a']),
+        (None, vec!["expected 5 bytes, found 1 at 2..3".to_owned()])
+    );
+}
+
+#[test]
+fn test_token_slice() {
+    use crate::context::tokens::TokenSlice;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Tok {
+        Let,
+        Ident,
+        Eq,
+        Num,
+    }
+
+    // "let  x = 42 ;"
+    let tokens = [
+        (Tok::Let, Span::new(0, 3)),
+        (Tok::Ident, Span::new(5, 6)),
+        (Tok::Eq, Span::new(7, 8)),
+        (Tok::Num, Span::new(9, 11)),
+    ];
+    let input = TokenSlice::with_eof(&tokens, 13);
+
+    let parser = chain((just(Tok::Let), just(Tok::Ident).spanned(), just(Tok::Eq)))
+        .to_slice()
+        .spanned();
+    let (output, errors): (_, Vec<String>) = parser.parse_input(input);
+    let (span, slice) = output.unwrap();
+    assert_eq!(span, Span::new(0, 8));
+    assert_eq!(slice.span(), Span::new(0, 8));
+    assert_eq!(slice.tokens(), &tokens[..3]);
+    assert!(errors.is_empty());
+
+    let (_, errors): (_, Vec<String>) = just(Tok::Let).parse_complete(input);
+    assert_eq!(
+        errors,
+        vec!["unexpected trailing input at 5..11".to_owned()]
+    );
+
+    let parser = chain((any(), any(), any(), any(), end().spanned())).map(|(.., (span, _))| span);
+    let (output, errors): (_, Vec<String>) = parser.parse_complete(input);
+    assert_eq!(output, Some(Span::new(13, 13)));
+    assert!(errors.is_empty());
+}
+
+#[test]
+fn test_lexer() {
+    use crate::context::tokens::TokenSlice;
+    use crate::context::Context;
+    use crate::lexer::{Lexer, MatchPolicy};
+    use crate::utils::{ident, string_literal, trivia, uint, StringConfig, TriviaConfig};
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Tok<'a> {
+        Let,
+        Ident(&'a str),
+        Num(u32),
+        Str,
+        Eq,
+        EqEq,
+        Error(&'a str),
+    }
+
+    let lexer = || {
+        Lexer::new(Tok::Error)
+            .rule(literal("let"), |_| Tok::Let)
+            .rule(ident(), Tok::Ident)
+            .rule(uint(10), Tok::Num)
+            .rule(string_literal(StringConfig::json()), |_| Tok::Str)
+            .rule(just('='), |_| Tok::Eq)
+            .rule(literal("=="), |_| Tok::EqEq)
+            .trivia(trivia(TriviaConfig::c()))
+    };
+
+    let input = "let letter = 1 // comment\n== @\"\\q\"";
+    let (tokens, errors): (_, Vec<String>) = lexer().lex(input);
+    assert_eq!(
+        tokens,
+        vec![
+            (Tok::Let, Span::new(0, 3)),
+            (Tok::Ident("letter"), Span::new(4, 10)),
+            (Tok::Eq, Span::new(11, 12)),
+            (Tok::Num(1), Span::new(13, 14)),
+            (Tok::EqEq, Span::new(26, 28)),
+            (Tok::Error("@"), Span::new(29, 30)),
+            (Tok::Str, Span::new(30, 34)),
+        ]
+    );
+    assert_eq!(errors, vec!["invalid escape sequence at 31..33".to_owned()]);
+
+    let (tokens, _): (_, Vec<String>) = lexer().policy(MatchPolicy::First).lex("letter==");
+    assert_eq!(
+        tokens,
+        vec![
+            (Tok::Let, Span::new(0, 3)),
+            (Tok::Ident("ter"), Span::new(3, 6)),
+            (Tok::Eq, Span::new(6, 7)),
+            (Tok::Eq, Span::new(7, 8)),
+        ]
+    );
+
+    let (tokens, errors): (_, Vec<String>) = lexer().lex("x \"abc");
+    assert_eq!(
+        tokens,
+        vec![
+            (Tok::Ident("x"), Span::new(0, 1)),
+            (Tok::Error("\""), Span::new(2, 3)),
+            (Tok::Ident("abc"), Span::new(3, 6)),
+        ]
+    );
+    assert_eq!(
+        errors,
+        vec!["unterminated string literal at 2..3".to_owned()]
+    );
+
+    let input = "let x = 42";
+    let (tokens, _): (_, Vec<String>) = lexer().lex(input);
+    let parser = chain((
+        just(Tok::Let),
+        pred(|tok| matches!(tok, Tok::Ident(_))),
+        just(Tok::Eq),
+        crate::primitive::any().spanned(),
+    ))
+    .map(|(_, name, _, value)| (name, value));
+    let (output, errors): (_, Vec<String>) =
+        parser.parse_complete(TokenSlice::with_eof(&tokens, input.len()));
+    assert_eq!(
+        output,
+        Some((Tok::Ident("x"), (Span::new(8, 10), Tok::Num(42))))
+    );
+    assert!(errors.is_empty());
+
+    let runs = std::cell::Cell::new(0);
+    let counted = func(|context: &mut Context<&str, String>| {
+        runs.set(runs.get() + 1);
+        just('a').parse(context)
+    });
+    let (tokens, _): (_, Vec<String>) = Lexer::new(|_| 'e')
+        .rule(counted, |c| c)
+        .rule(just('b'), |c| c)
+        .lex("aab");
+    assert_eq!(tokens.len(), 3);
+    assert_eq!(runs.get(), 3);
+}
+
+#[test]
+fn test_select() {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Tok {
+        Ident(&'static str),
+        Int(i64),
+        Plus,
+    }
+
+    fn parse<Out>(
+        parser: impl Parser<&'static [Tok], Out, String>,
+        input: &'static [Tok],
+    ) -> (Option<Out>, Vec<String>) {
+        parser.parse_input(input)
+    }
+
+    let atom = || {
+        select! {
+            Tok::Ident(name) => name.to_owned(),
+            Tok::Int(n) if n >= 0 => n.to_string(),
+        }
+    };
+
+    assert_eq!(
+        parse(atom(), &[Tok::Ident("x")]),
+        (Some("x".to_owned()), vec![])
+    );
+    assert_eq!(
+        parse(atom(), &[Tok::Int(3)]),
+        (Some("3".to_owned()), vec![])
+    );
+    assert_eq!(parse(atom(), &[Tok::Int(-3)]), (None, vec![]));
+    assert_eq!(parse(atom(), &[Tok::Plus]), (None, vec![]));
+
+    let either = choice((atom(), just(Tok::Plus).map(|_| "+".to_owned())));
+    assert_eq!(parse(either, &[Tok::Plus]), (Some("+".to_owned()), vec![]));
+
+    assert_eq!(
+        parse(atom().or_error(), &[Tok::Plus]),
+        (
+            None,
+            vec!["expected `Tok::Ident(name)` or `Tok::Int(n)` at 0..1".to_owned()]
+        )
+    );
+    assert_eq!(
+        parse(select! { Tok::Plus => () }.or_error(), &[]),
+        (None, vec!["expected `Tok::Plus` at 0..0".to_owned()])
+    );
+}
+
+#[test]
+fn test_stream() {
+    use crate::context::stream::{Stream, StreamSlice};
+    use crate::utils::uint;
+
+    // Chunks split in the middle of a character and of a line.
+    let stream = Stream::from_chunks(["h\u{e9}llo\nw".as_bytes(), b"orld\n\xc3", b"\xa9\n"]);
+    let line = chain((pred(|c| c != '\n').repeat().collect::<String>(), just('\n')))
+        .map_with(|(line, _), extra| (line, extra.span()));
+    let mut lines = vec![];
+    while let (Some(line), errors) = stream.parse_next::<char, _, String>(&line) {
+        assert!(errors.is_empty());
+        lines.push(line);
+        assert!(stream.buffered() <= 14);
+    }
+    assert_eq!(
+        lines,
+        vec![
+            ("h\u{e9}llo".to_owned(), Span::new(0, 7)),
+            ("world".to_owned(), Span::new(7, 13)),
+            ("\u{e9}".to_owned(), Span::new(13, 16)),
+        ]
+    );
+
+    let stream = Stream::from_read(&b"\x01\x02\xff"[..]);
+    let bytes = crate::primitive::any().repeat().collect::<Vec<u8>>();
+    let (output, errors) = stream.parse_next::<u8, _, String>(&bytes);
+    assert_eq!(output, Some(vec![1, 2, 255]));
+    assert!(errors.is_empty());
+
+    let stream = Stream::pending();
+    let number = chain((uint::<_, String, u32>(10), just(' '))).map(|(n, _)| n);
+    stream.feed(b"12");
+    assert_eq!(
+        stream.parse_next(&number),
+        (
+            None,
+            vec!["incomplete input, need more data at 0..2".to_owned()]
+        )
+    );
+    stream.feed(b"3 4");
+    assert_eq!(stream.parse_next(&number), (Some(123), vec![]));
+    assert_eq!(stream.offset(), 4);
+    assert_eq!(
+        stream.parse_next(&number),
+        (
+            None,
+            vec!["incomplete input, need more data at 4..5".to_owned()]
+        )
+    );
+    stream.close();
+    assert_eq!(stream.parse_next(&number), (None, vec![]));
+
+    // Slices can be copied out of the stream.
+    let stream = Stream::from_chunks(["foo ", "bar baz"]);
+    let word = chain((crate::utils::ident(), crate::utils::space()))
+        .map(|(word, _): (StreamSlice<_, char>, _)| word.to_string());
+    assert_eq!(
+        stream.parse_next::<_, _, String>(&word),
+        (Some("foo".to_owned()), vec![])
+    );
+    let bytes = take_while(|b: u8| b != b' ').map(|word: StreamSlice<_, u8>| word.to_vec());
+    assert_eq!(
+        stream.parse_next::<_, _, String>(&bytes),
+        (Some(b"bar".to_vec()), vec![])
+    );
+
+    struct Failing;
+
+    impl std::io::Read for Failing {
+        fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::other("disk on fire"))
+        }
+    }
+
+    let stream = Stream::from_read(Failing);
+    assert_eq!(
+        stream.parse_next::<u8, _, String>(&crate::primitive::any()),
+        (
+            None,
+            vec!["couldn't read input: disk on fire at 0..0".to_owned()]
+        )
+    );
+    assert_eq!(
+        stream.take_error().map(|err| err.to_string()).as_deref(),
+        Some("disk on fire")
+    );
+
+    // The failure is reported again, rather than treated as the end of the
+    // input.
+    assert_eq!(
+        stream.parse_next::<u8, _, String>(&end()),
+        (
+            None,
+            vec!["couldn't read input: disk on fire at 0..0".to_owned()]
+        )
+    );
+}
+
+#[test]
+fn test_shared() {
+    use crate::context::shared::{Shared, SharedSlice};
+    use crate::utils::{ident, space};
+
+    let source = Shared::from(String::from("foo \u{e9}t\u{e9} baz"));
+    let (output, errors): (_, Vec<String>) = {
+        let idents = chain((space(), ident().spanned(), space()))
+            .map(|(_, (span, ident), _): (_, (_, SharedSlice<str>), _)| (span, ident.to_shared()))
+            .repeat()
+            .collect::<Vec<_>>();
+        source.parse(&idents)
+    };
+    drop(source);
+    let output = output.unwrap();
+    assert!(errors.is_empty());
+    assert_eq!(output[0], (Span::new(0, 3), Shared::from("foo")));
+    assert_eq!(output[1].1, "\u{e9}t\u{e9}");
+    assert_eq!(output[2].1.span(), Span::new(10, 13));
+    assert_eq!(output[2].1.source().as_ref(), "foo \u{e9}t\u{e9} baz");
+
+    // Parsing part of a source gives spans in the whole source.
+    let (output, _): (_, Vec<String>) = output[1].1.parse(&ident().spanned());
+    assert_eq!(output.map(|(span, _)| span), Some(Span::new(4, 9)));
+
+    let bytes = Shared::from(vec![1u8, 2, 3, 4]);
+    let (output, _): (_, Vec<String>) = bytes.parse(
+        &chain((just(1), crate::primitive::any().repeat().to_slice()))
+            .map(|(_, rest): (_, SharedSlice<[u8]>)| rest.to_shared()),
+    );
+    assert_eq!(output.as_deref(), Some(&[2, 3, 4][..]));
+}
+
+#[cfg(feature = "ropey")]
+#[test]
+fn test_rope() {
+    use ropey::{Rope, RopeSlice};
+
+    use crate::context::slice::Slice;
+    use crate::utils::{ident, space};
+
+    let text = "\u{e9}t\u{e9} ".repeat(1000);
+    let rope = Rope::from_str(&text);
+    let input = rope.slice(..);
+    assert!(input.chunks().count() > 1);
+
+    let idents = chain((ident().spanned(), space()))
+        .map(|((span, ident), _): ((Span, RopeSlice), _)| {
+            assert_eq!(ident, "\u{e9}t\u{e9}");
+            span
+        })
+        .repeat()
+        .collect::<Vec<_>>();
+    let (output, errors): (_, Vec<String>) = idents.parse_complete(input);
+    let spans = output.unwrap();
+    assert!(errors.is_empty());
+    assert_eq!(spans.len(), 1000);
+    assert_eq!(spans[999], Span::new(999 * 6, 999 * 6 + 5));
+
+    // A slice that crosses chunk boundaries.
+    let all = take_while(|c| c != '!').parse_input(input);
+    assert_eq!(all, (Some(input), Vec::<String>::new()));
+    // Byte offsets must be on character boundaries.
+    assert_eq!(Slice::slice(&input, 1, 3), None);
+    assert_eq!(Slice::slice(&input, 2, 3), Some(rope.slice(1..2)));
+}
+
+#[test]
+fn test_located() {
+    use crate::context::located::{LineIndex, Located, Unit};
+    use crate::utils::ident;
+
+    let text = "ab \u{1f600}\ncd\u{e9}\n\n ef";
+    let idents = |index| {
+        let (output, errors): (_, Vec<String>) =
+            chain((take_while(|c: char| !c.is_alphabetic()), ident().spanned()))
+                .map(|(_, (span, _)): (_, (_, Located))| span)
+                .repeat()
+                .collect::<Vec<_>>()
+                .parse_input(LineIndex::slice(index));
+        assert!(errors.is_empty());
+        output.unwrap()
+    };
+
+    let index = LineIndex::new(text, Unit::Byte);
+    assert_eq!(
+        idents(&index),
+        vec![Span::new(0, 2), Span::new(8, 12), Span::new(15, 17)]
+    );
+
+    // The emoji is one character, but two UTF-16 code units.
+    let index = LineIndex::new(text, Unit::Char);
+    assert_eq!(
+        idents(&index),
+        vec![Span::new(0, 2), Span::new(5, 8), Span::new(11, 13)]
+    );
+
+    let index = LineIndex::new(text, Unit::Utf16);
+    let spans = idents(&index);
+    assert_eq!(
+        spans,
+        vec![Span::new(0, 2), Span::new(6, 9), Span::new(12, 14)]
+    );
+
+    let position = index.position(spans[1].end());
+    assert_eq!((position.line(), position.column(Unit::Utf16)), (1, 3));
+    assert_eq!(position.column(Unit::Byte), 4);
+    assert_eq!(position.offset(Unit::Char), 8);
+
+    let position = index.position(spans[2].start());
+    assert_eq!((position.line(), position.column(Unit::Char)), (3, 1));
+
+    // The middle of the emoji.
+    let position = index.position(4);
+    assert_eq!(position.offset(Unit::Utf16), 3);
+    assert_eq!(position.column(Unit::Byte), 3);
+
+    // Spans on a long line don't rescan it from the start each time.
+    let text = format!("{}\nend", "\u{e9}x".repeat(20_000));
+    let index = LineIndex::new(&text, Unit::Char);
+    let (spans, _): (_, Vec<String>) = crate::primitive::any()
+        .spanned()
+        .map(|(span, _)| span)
+        .repeat()
+        .collect::<Vec<_>>()
+        .parse_input(index.slice());
+    let spans = spans.unwrap();
+    assert_eq!(spans.len(), 40_004);
+    assert!(spans
+        .iter()
+        .enumerate()
+        .all(|(i, &span)| span == Span::new(i, i + 1)));
+
+    let position = index.position(39_999);
+    assert_eq!((position.line(), position.column(Unit::Byte)), (0, 59_999));
+    let position = index.position(40_002);
+    assert_eq!((position.line(), position.column(Unit::Char)), (1, 1));
+}
+
+#[test]
+fn test_max_depth() {
+    use crate::context::Context;
+
+    fn nested<'a>() -> impl Parser<&'a str, usize, String> + 'a {
+        recursive(|nested| {
+            choice((
+                chain((just('['), nested, just(']'))).map(|(_, depth, _)| depth + 1),
+                nothing().map(|_| 0),
+            ))
+        })
+    }
+
+    fn parse(input: &str) -> (Option<usize>, Vec<String>) {
+        let mut context = Context::new(input).with_max_depth(10);
+        let output = nested().parse_with(&mut context);
+        assert_eq!(context.depth(), 0);
+        (output, context.take_errors())
+    }
+
+    let input = format!("{}{}", "[".repeat(9), "]".repeat(9));
+    assert_eq!(parse(&input), (Some(9), vec![]));
+
+    let input = format!("{}{}", "[".repeat(10), "]".repeat(10));
+    assert_eq!(
+        parse(&input),
+        (
+            None,
+            vec!["nesting too deep (the limit is 10) at 10..10".to_owned()]
+        )
+    );
+
+    // Without a limit, the stack grows as needed.
+    #[cfg(feature = "stacker")]
+    {
+        let input = format!("{}{}", "[".repeat(100_000), "]".repeat(100_000));
+        let (output, errors) = nested().parse_input(&input);
+        assert_eq!(output, Some(100_000));
+        assert!(errors.is_empty());
+    }
+}
+
+#[test]
+fn test_limits() {
+    use crate::context::{Context, Limits};
+
+    let many_a = || just('a').repeat().collect::<String>();
+    let input = "a".repeat(1000);
+    let input = input.as_str();
+
+    let (output, errors): (_, Vec<String>) = many_a().parse_with_limits(
+        input,
+        Limits {
+            fuel: Some(1001),
+            ..Limits::default()
+        },
+    );
+    assert_eq!(output.map(|s| s.len()), Some(1000));
+    assert!(errors.is_empty());
+
+    let (output, errors): (_, Vec<String>) = many_a().parse_with_limits(
+        input,
+        Limits {
+            fuel: Some(100),
+            ..Limits::default()
+        },
+    );
+    assert_eq!(output, None);
+    assert_eq!(
+        errors,
+        vec!["parse took too many steps at 100..100".to_owned()]
+    );
+
+    let mut context = Context::<_, String>::new("ab").with_fuel(10);
+    chain((just('a'), just('b'))).parse_with(&mut context);
+    assert_eq!(context.fuel(), Some(8));
+
+    let report_x = func(|context: &mut Context<&str, String>| {
+        let c = crate::primitive::any().parse(context)?;
+        if c == 'x' {
+            context.report(format!("bad x at {}", context.location() - 1));
+        }
+        Ok(c)
+    });
+    let (output, errors) = report_x.repeat().collect::<String>().parse_with_limits(
+        "axbxcxdx",
+        Limits {
+            max_errors: Some(2),
+            ..Limits::default()
+        },
+    );
+    assert_eq!(output, None);
+    assert_eq!(
+        errors,
+        vec![
+            "bad x at 1".to_owned(),
+            "bad x at 3".to_owned(),
+            "too many errors (the limit is 2) at 4..4".to_owned(),
+        ]
+    );
+
+    // Lexing stops when the fuel runs out, instead of making error tokens.
+    let lexer = crate::lexer::Lexer::new(|_| ' ')
+        .rule(crate::primitive::any(), |c| c)
+        .trivia(just(' '));
+    let mut context = Context::<_, String>::new("abcdefghij").with_fuel(10);
+    assert_eq!(lexer.parse_with(&mut context), None);
+    assert_eq!(
+        context.take_errors(),
+        vec!["parse took too many steps at 5..5".to_owned()]
+    );
+    assert!(context.is_exhausted());
+
+    // A rule's own error on the last step isn't mistaken for running out.
+    let input = "x".repeat(10_000);
+    let bad_x = func(|context: &mut Context<&str, String>| {
+        let start = context.location();
+        match crate::primitive::any().parse(context)? {
+            'x' => Err(Some(format!("bad x at {start}"))),
+            c => Ok(c),
+        }
+    });
+    let lexer = crate::lexer::Lexer::new(|_| '?').rule(bad_x, |c| c);
+    let mut context = Context::<_, String>::new("axa").with_fuel(4);
+    assert_eq!(lexer.parse_with(&mut context), None);
+    assert_eq!(
+        context.take_errors(),
+        vec![
+            "bad x at 1".to_owned(),
+            "parse took too many steps at 2..2".to_owned(),
+        ]
+    );
+
+    // Lexing stops at the error limit too.
+    let mut context = Context::<_, String>::new(input.as_str()).with_max_errors(3);
+    assert_eq!(lexer.parse_with(&mut context), None);
+    assert_eq!(context.take_errors().len(), 4);
+    assert_eq!(context.location(), 2);
+
+    // Parsers outside papa can use fuel too.
+    struct Step;
+
+    impl<'a> Parser<&'a str, (), String> for Step {
+        fn parse(&self, context: &mut Context<&'a str, String>) -> ParseResult<(), String> {
+            context.step()
+        }
+    }
+
+    let mut context = Context::new("").with_fuel(1);
+    assert_eq!(Step.parse_with(&mut context), Some(()));
+    assert_eq!(Step.parse_with(&mut context), None);
+    assert_eq!(context.errors().len(), 1);
+
+    let nested =
+        recursive(|nested| choice((chain((just('('), nested, just(')'))).drop(), nothing())));
+    let (_, errors): (_, Vec<String>) = nested.parse_with_limits(
+        "((()))",
+        Limits {
+            max_depth: Some(3),
+            ..Limits::default()
+        },
+    );
+    assert_eq!(
+        errors,
+        vec!["nesting too deep (the limit is 3) at 3..3".to_owned()]
+    );
+}
+
+#[cfg(feature = "trace")]
+#[test]
+fn test_trace() {
+    use crate::context::Context;
+    use crate::trace::Outcome;
+
+    let digits = take_while1(|c: char| c.is_ascii_digit()).traced("digits");
+    let word = take_while1(char::is_alphabetic).traced("word");
+    let item = choice((digits, word)).traced("item");
+    let parser = chain((item.clone(), just(' '), item)).traced("pair");
+
+    let (output, errors, trace): (_, Vec<String>, _) = parser.parse_traced("ab 12");
+    assert_eq!(output, Some(("ab", ' ', "12")));
+    assert!(errors.is_empty());
+
+    let [pair] = trace.events() else {
+        panic!("expected one event");
+    };
+    assert_eq!(pair.name(), "pair");
+    assert_eq!(pair.outcome(), Outcome::Matched);
+    assert_eq!(pair.consumed(), "ab 12");
+    assert_eq!(pair.span(), Span::new(0, 5));
+
+    let [first, second] = pair.children() else {
+        panic!("expected two items");
+    };
+    assert_eq!(first.children().len(), 2);
+    assert_eq!(first.children()[0].outcome(), Outcome::NoMatch);
+    assert_eq!(first.children()[1].children()[0].consumed(), "ab");
+    assert_eq!(second.start(), 3);
+    assert_eq!(second.consumed(), "12");
+
+    assert_eq!(
+        trace.to_string(),
+        "\
+pair at 0: matched \"ab 12\" at 0..5
+  item at 0: matched \"ab\" at 0..2
+    choice 0 at 0: no match
+      digits at 0: no match
+    choice 1 at 0: matched \"ab\" at 0..2
+      word at 0: matched \"ab\" at 0..2
+  item at 3: matched \"12\" at 3..5
+    choice 0 at 3: matched \"12\" at 3..5
+      digits at 3: matched \"12\" at 3..5
+"
+    );
+
+    let json = trace.to_json();
+    assert!(json.starts_with(
+        r#"[{"name":"pair","start":0,"span":[0,5],"outcome":"matched","children":[{"name":"item""#
+    ));
+    assert!(json.ends_with("]}]}]"));
+    assert!(trace.to_json_with_text().starts_with(
+        r#"[{"name":"pair","start":0,"span":[0,5],"consumed":"ab 12","outcome":"matched","#
+    ));
+
+    // Tracing is off unless enabled on the context.
+    let mut context = Context::<_, String>::new("12");
+    assert_eq!(parser.parse_with(&mut context), None);
+    assert!(context.take_trace().is_none());
+}
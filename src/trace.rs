@@ -0,0 +1,240 @@
+//! Record what parsers did, to debug a grammar.
+//!
+//! Tracing is enabled with the `trace` feature and [`Context::with_trace`].
+//! Each [`Parser::traced`] parser, and each branch of a [`choice`], then
+//! records an [`Event`] saying where it was tried and whether it matched.
+//! Other parsers, including primitives, aren't recorded, so wrap the parts of
+//! a grammar to be inspected with [`Parser::traced`]. Without the feature,
+//! [`Parser::traced`] does nothing.
+//!
+//! [`Context::with_trace`]: crate::context::Context::with_trace
+//! [`Parser::traced`]: crate::parser::Parser::traced
+//! [`choice`]: crate::combinator::choice::choice
+
+use std::fmt::{self, Write};
+
+use crate::context::slice::Slice;
+use crate::context::span::Span;
+
+/// How a traced parser finished.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Outcome {
+    Matched,
+    NoMatch,
+    /// The parser failed with an error.
+    Error,
+}
+
+impl Outcome {
+    fn as_str(self) -> &'static str {
+        match self {
+            Outcome::Matched => "matched",
+            Outcome::NoMatch => "no match",
+            Outcome::Error => "error",
+        }
+    }
+}
+
+/// A traced parser being run, along with the traced parsers it ran.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event<In> {
+    name: &'static str,
+    start: usize,
+    span: Span,
+    consumed: In,
+    outcome: Outcome,
+    children: Vec<Event<In>>,
+}
+
+impl<In: Slice> Event<In> {
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// The location where the parser was entered.
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// The span of the input consumed, which is empty unless the parser
+    /// matched.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// The input consumed, which is empty unless the parser matched.
+    pub fn consumed(&self) -> In {
+        self.consumed
+    }
+
+    pub fn outcome(&self) -> Outcome {
+        self.outcome
+    }
+
+    /// The events of the traced parsers run by this one, in order.
+    pub fn children(&self) -> &[Event<In>] {
+        &self.children
+    }
+}
+
+/// The events recorded by a traced parse.
+///
+/// Displays as an indented log, with one line per event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trace<In> {
+    events: Vec<Event<In>>,
+}
+
+impl<In: Slice> Trace<In> {
+    /// The top-level events, in order.
+    pub fn events(&self) -> &[Event<In>] {
+        &self.events
+    }
+
+    /// Export the trace as JSON, as an array of event objects.
+    ///
+    /// Each event has the fields `name`, `start`, `span` (as `[start, end]`),
+    /// `outcome` and `children`.
+    pub fn to_json(&self) -> String {
+        let mut json = String::new();
+        write_json_events(&mut json, &self.events, None);
+        json
+    }
+
+    /// Export the trace as JSON, like [`Trace::to_json`], with the text each
+    /// event consumed in a `consumed` field.
+    pub fn to_json_with_text(&self) -> String
+    where
+        In: AsRef<str>,
+    {
+        let mut json = String::new();
+        write_json_events(&mut json, &self.events, Some(<In as AsRef<str>>::as_ref));
+        json
+    }
+}
+
+impl<In: Slice + fmt::Debug> fmt::Display for Trace<In> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn write_events<In: Slice + fmt::Debug>(
+            f: &mut fmt::Formatter<'_>,
+            events: &[Event<In>],
+            depth: usize,
+        ) -> fmt::Result {
+            for event in events {
+                write!(
+                    f,
+                    "{:indent$}{} at {}: {}",
+                    "",
+                    event.name,
+                    event.start,
+                    event.outcome.as_str(),
+                    indent = depth * 2,
+                )?;
+                if event.outcome == Outcome::Matched {
+                    write!(f, " {:?} at {:?}", event.consumed, event.span)?;
+                }
+                writeln!(f)?;
+                write_events(f, &event.children, depth + 1)?;
+            }
+            Ok(())
+        }
+
+        write_events(f, &self.events, 0)
+    }
+}
+
+fn write_json_events<In: Slice>(
+    json: &mut String,
+    events: &[Event<In>],
+    text: Option<fn(&In) -> &str>,
+) {
+    json.push('[');
+    for (i, event) in events.iter().enumerate() {
+        if i > 0 {
+            json.push(',');
+        }
+        json.push_str("{\"name\":");
+        write_json_string(json, event.name);
+        let _ = write!(
+            json,
+            ",\"start\":{},\"span\":[{},{}]",
+            event.start,
+            event.span.start(),
+            event.span.end(),
+        );
+        if let Some(text) = text {
+            json.push_str(",\"consumed\":");
+            write_json_string(json, text(&event.consumed));
+        }
+        json.push_str(",\"outcome\":");
+        write_json_string(json, event.outcome.as_str());
+        json.push_str(",\"children\":");
+        write_json_events(json, &event.children, text);
+        json.push('}');
+    }
+    json.push(']');
+}
+
+fn write_json_string(json: &mut String, s: &str) {
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(json, "\\u{:04x}", c as u32);
+            }
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+}
+
+/// Builds a [`Trace`] while parsing.
+pub(crate) struct Tracer<In> {
+    events: Vec<Event<In>>,
+    /// The events that have been entered but not exited yet, innermost last.
+    open: Vec<Event<In>>,
+}
+
+impl<In: Slice> Tracer<In> {
+    pub(crate) fn new() -> Self {
+        Self {
+            events: vec![],
+            open: vec![],
+        }
+    }
+
+    pub(crate) fn enter(&mut self, name: &'static str, start: usize, input: In) {
+        self.open.push(Event {
+            name,
+            start,
+            span: Span::new(start, start),
+            consumed: input.slice(start, start).expect("couldn't get slice"),
+            outcome: Outcome::NoMatch,
+            children: vec![],
+        });
+    }
+
+    pub(crate) fn exit(&mut self, outcome: Outcome, span: Span, consumed: In) {
+        let mut event = self.open.pop().expect("exited a trace event twice");
+        event.outcome = outcome;
+        event.span = span;
+        event.consumed = consumed;
+
+        match self.open.last_mut() {
+            Some(parent) => parent.children.push(event),
+            None => self.events.push(event),
+        }
+    }
+
+    pub(crate) fn finish(self) -> Trace<In> {
+        assert!(self.open.is_empty(), "trace taken during a parse");
+        Trace {
+            events: self.events,
+        }
+    }
+}
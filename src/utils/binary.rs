@@ -0,0 +1,214 @@
+//! Parsers for binary formats, with input such as `&[u8]`.
+//!
+//! A value that is cut off by the end of the input is reported as an error,
+//! but a parser that finds no input at all just fails to match, so that
+//! something like `be_u32().repeat()` stops at the end of the input.
+
+use crate::context::slice::Slice;
+use crate::context::Context;
+use crate::error::{ErrorKind, ParseError};
+use crate::prelude::*;
+
+macro_rules! fixed_width {
+    ($($name:ident: $ty:ty = $from_bytes:ident, $order:literal;)*) => {
+        $(
+            #[doc = concat!("Parse a ", $order, " `", stringify!($ty), "`.")]
+            pub fn $name<In, Error>() -> impl Parser<In, $ty, Error>
+            where
+                In: Slice<Token = u8>,
+                Error: From<ParseError>,
+            {
+                func(|context: &mut Context<In, Error>| read_array(context).map(<$ty>::$from_bytes))
+            }
+        )*
+    };
+}
+
+fixed_width! {
+    be_u16: u16 = from_be_bytes, "big-endian";
+    le_u16: u16 = from_le_bytes, "little-endian";
+    be_u32: u32 = from_be_bytes, "big-endian";
+    le_u32: u32 = from_le_bytes, "little-endian";
+    be_u64: u64 = from_be_bytes, "big-endian";
+    le_u64: u64 = from_le_bytes, "little-endian";
+    be_u128: u128 = from_be_bytes, "big-endian";
+    le_u128: u128 = from_le_bytes, "little-endian";
+    be_i16: i16 = from_be_bytes, "big-endian";
+    le_i16: i16 = from_le_bytes, "little-endian";
+    be_i32: i32 = from_be_bytes, "big-endian";
+    le_i32: i32 = from_le_bytes, "little-endian";
+    be_i64: i64 = from_be_bytes, "big-endian";
+    le_i64: i64 = from_le_bytes, "little-endian";
+    be_i128: i128 = from_be_bytes, "big-endian";
+    le_i128: i128 = from_le_bytes, "little-endian";
+    be_f32: f32 = from_be_bytes, "big-endian";
+    le_f32: f32 = from_le_bytes, "little-endian";
+    be_f64: f64 = from_be_bytes, "big-endian";
+    le_f64: f64 = from_le_bytes, "little-endian";
+}
+
+/// Match `n` tokens, outputting the matched slice.
+pub fn take<In, Error>(n: usize) -> impl Parser<In, In, Error>
+where
+    In: Slice<Token = u8>,
+    Error: From<ParseError>,
+{
+    func(move |context: &mut Context<In, Error>| {
+        if n > 0 && context.slice_current().is_empty() {
+            return Err(None);
+        }
+        take_exact(context, n)
+    })
+}
+
+/// Parse an unsigned LEB128 integer, as used for varints in Protocol
+/// Buffers and WebAssembly.
+///
+/// Reports an error if the integer doesn't fit in a `u64`.
+pub fn uleb128<In, Error>() -> impl Parser<In, u64, Error>
+where
+    In: Slice<Token = u8>,
+    Error: From<ParseError>,
+{
+    func(|context: &mut Context<In, Error>| {
+        let start = context.location();
+        let (value, _) = leb128(context)?;
+        u64::try_from(value).map_err(|_| Some(out_of_range(context, start).into()))
+    })
+}
+
+/// Parse a signed LEB128 integer.
+///
+/// Reports an error if the integer doesn't fit in an `i64`.
+pub fn sleb128<In, Error>() -> impl Parser<In, i64, Error>
+where
+    In: Slice<Token = u8>,
+    Error: From<ParseError>,
+{
+    func(|context: &mut Context<In, Error>| {
+        let start = context.location();
+        let (value, bits) = leb128(context)?;
+
+        // Sign-extend from the last bit that was read.
+        let value = (value << (128 - bits)) as i128 >> (128 - bits);
+        i64::try_from(value).map_err(|_| Some(out_of_range(context, start).into()))
+    })
+}
+
+/// Parse a length with `len`, then parse exactly that many tokens with
+/// `body`.
+///
+/// `body` can't see past the end of the length-prefixed input, and it's an
+/// error if it doesn't consume all of it.
+pub fn length_prefixed<In, Len, Out, Error>(
+    len: impl Parser<In, Len, Error>,
+    body: impl Parser<In, Out, Error>,
+) -> impl Parser<In, Out, Error>
+where
+    In: Slice<Token = u8>,
+    Len: TryInto<usize>,
+    Error: From<ParseError>,
+{
+    func(move |context: &mut Context<In, Error>| {
+        let len = len.parse(context)?;
+        let len = len.try_into().unwrap_or(usize::MAX);
+        let start = context.location();
+        take_exact(context, len)?;
+        let end = context.location();
+
+        context.set_location(start);
+        let output = context.with_end(end, |context| body.parse(context))?;
+        if context.location() != end {
+            let span = context.span(context.location(), end);
+            return Err(Some(ParseError::new(ErrorKind::TrailingInput, span).into()));
+        }
+
+        Ok(output)
+    })
+}
+
+/// Read a fixed number of bytes.
+fn read_array<In, Error, const N: usize>(
+    context: &mut Context<In, Error>,
+) -> ParseResult<[u8; N], Error>
+where
+    In: Slice<Token = u8>,
+    Error: From<ParseError>,
+{
+    if context.slice_current().is_empty() {
+        return Err(None);
+    }
+
+    let mut bytes = take_exact(context, N)?;
+    Ok(std::array::from_fn(|_| {
+        let (byte, rest) = bytes.next().expect("slice too short");
+        bytes = rest;
+        byte
+    }))
+}
+
+/// Match exactly `n` tokens, reporting an error if the input is too short.
+fn take_exact<In, Error>(context: &mut Context<In, Error>, n: usize) -> ParseResult<In, Error>
+where
+    In: Slice<Token = u8>,
+    Error: From<ParseError>,
+{
+    let start = context.location();
+    let current = context.slice_current();
+    let Some(taken) = current.slice(0, n) else {
+        let span = context.span(start, start + current.len());
+        let kind = ErrorKind::Truncated {
+            expected: n,
+            found: current.len(),
+        };
+        return Err(Some(ParseError::new(kind, span).into()));
+    };
+
+    context.set_location(start + n);
+    Ok(taken)
+}
+
+/// Match a LEB128 integer, outputting its bits and how many there are.
+///
+/// Reports an error if there are too many bits to fit in a `u128`.
+fn leb128<In, Error>(context: &mut Context<In, Error>) -> ParseResult<(u128, u32), Error>
+where
+    In: Slice<Token = u8>,
+    Error: From<ParseError>,
+{
+    let start = context.location();
+    let current = context.slice_current();
+    if current.is_empty() {
+        return Err(None);
+    }
+
+    let len = current.prefix_len(|byte| byte & 0x80 != 0) + 1;
+    if len > current.len() {
+        let span = context.span(start, start + current.len());
+        let kind = ErrorKind::Truncated {
+            expected: len,
+            found: current.len(),
+        };
+        return Err(Some(ParseError::new(kind, span).into()));
+    }
+
+    context.set_location(start + len);
+    if len > 128 / 7 {
+        return Err(Some(out_of_range(context, start).into()));
+    }
+
+    let mut value = 0;
+    let mut bits = 0;
+    let mut rest = current;
+    for _ in 0..len {
+        let (byte, after) = rest.next().expect("slice too short");
+        value |= u128::from(byte & 0x7f) << bits;
+        bits += 7;
+        rest = after;
+    }
+    Ok((value, bits))
+}
+
+fn out_of_range<In: Slice, Error>(context: &Context<In, Error>, start: usize) -> ParseError {
+    ParseError::new(ErrorKind::IntegerOutOfRange, context.span_from(start))
+}
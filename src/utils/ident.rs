@@ -0,0 +1,138 @@
+use std::marker::PhantomData;
+
+use crate::context::slice::Slice;
+use crate::context::Context;
+use crate::prelude::*;
+
+/// Parse an identifier, outputting the matched slice.
+///
+/// By default, identifiers follow [UAX #31]: they start with a character
+/// with the `XID_Start` property or `_`, followed by characters with the
+/// `XID_Continue` property. The rules can be changed with the methods on
+/// [`Ident`], but `_` is always allowed anywhere in an identifier.
+///
+/// [UAX #31]: https://www.unicode.org/reports/tr31/
+pub fn ident<In: Slice<Token = char>, Error>() -> Ident<In, Error> {
+    Ident {
+        unicode: true,
+        start: "",
+        continue_: "",
+        medial: "",
+        _phantom: PhantomData,
+    }
+}
+
+#[derive_where::derive_where(Debug, Clone, Copy)]
+pub struct Ident<In: Slice<Token = char>, Error> {
+    unicode: bool,
+    start: &'static str,
+    continue_: &'static str,
+    medial: &'static str,
+    _phantom: PhantomData<*const (In, Error)>,
+}
+
+impl<In: Slice<Token = char>, Error> Ident<In, Error> {
+    /// Only allow ASCII letters, digits and `_` (plus any extra characters).
+    pub fn ascii(mut self) -> Self {
+        self.unicode = false;
+        self
+    }
+
+    /// Allow these characters anywhere in an identifier, such as `$`.
+    ///
+    /// `_` is always allowed.
+    pub fn extra_chars(mut self, chars: &'static str) -> Self {
+        self.start = chars;
+        self.continue_ = chars;
+        self
+    }
+
+    /// Allow these characters at the start of an identifier, such as a
+    /// leading `@`.
+    ///
+    /// This replaces any characters set with [`Ident::extra_chars`], but `_`
+    /// is always allowed.
+    pub fn start_chars(mut self, chars: &'static str) -> Self {
+        self.start = chars;
+        self
+    }
+
+    /// Allow these characters after the start of an identifier.
+    ///
+    /// This replaces any characters set with [`Ident::extra_chars`], but `_`
+    /// is always allowed.
+    pub fn continue_chars(mut self, chars: &'static str) -> Self {
+        self.continue_ = chars;
+        self
+    }
+
+    /// Allow these characters in the middle of an identifier, between two
+    /// other characters, such as `-` in CSS or Lisp.
+    pub fn medial_chars(mut self, chars: &'static str) -> Self {
+        self.medial = chars;
+        self
+    }
+
+    fn is_start(&self, c: char) -> bool {
+        if c.is_ascii() {
+            c.is_ascii_alphabetic() || c == '_' || self.start.contains(c)
+        } else {
+            (self.unicode && unicode_ident::is_xid_start(c)) || self.start.contains(c)
+        }
+    }
+
+    fn is_continue(&self, c: char) -> bool {
+        if c.is_ascii() {
+            c.is_ascii_alphanumeric() || c == '_' || self.continue_.contains(c)
+        } else {
+            (self.unicode && unicode_ident::is_xid_continue(c)) || self.continue_.contains(c)
+        }
+    }
+
+    /// Match an identifier at the start of `input`, outputting its length
+    /// and calling `visit` on each of its characters.
+    pub(super) fn scan(&self, input: In, mut visit: impl FnMut(char)) -> Option<usize> {
+        let mut rest = match input.next() {
+            Some((c, rest)) if self.is_start(c) => {
+                visit(c);
+                rest
+            }
+            _ => return None,
+        };
+
+        loop {
+            let len = rest.prefix_len(|c| {
+                let is_continue = self.is_continue(c);
+                if is_continue {
+                    visit(c);
+                }
+                is_continue
+            });
+            rest = rest.slice_from(len).expect("couldn't get slice");
+
+            match rest.next() {
+                Some((c, after)) if self.medial.contains(c) => match after.next() {
+                    Some((next, _)) if self.is_continue(next) => {
+                        visit(c);
+                        rest = after;
+                    }
+                    _ => break,
+                },
+                _ => break,
+            }
+        }
+
+        Some(input.len() - rest.len())
+    }
+}
+
+impl<In: Slice<Token = char>, Error> Parser<In, In, Error> for Ident<In, Error> {
+    fn parse(&self, context: &mut Context<In, Error>) -> ParseResult<In, Error> {
+        context.step()?;
+        let current = context.slice_current();
+        let len = self.scan(current, |_| {}).ok_or(None)?;
+
+        context.set_location(context.location() + len);
+        Ok(current.slice(0, len).expect("couldn't get slice"))
+    }
+}
@@ -0,0 +1,284 @@
+//! Parsers for indentation-sensitive grammars.
+//!
+//! The indentation levels of the enclosing blocks are kept in an
+//! [`IndentStack`], stored as state in the [`Context`]. It is created on
+//! demand, and rewound on backtracking like any other state.
+//!
+//! Indentation is measured in spaces and tabs, each counting as one column.
+
+use crate::context::slice::Slice;
+use crate::context::state::State;
+use crate::context::Context;
+use crate::error::{ErrorKind, ParseError};
+use crate::prelude::*;
+
+/// The indentation levels of the enclosing blocks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndentStack {
+    levels: Vec<usize>,
+    joining: usize,
+}
+
+impl IndentStack {
+    /// The indentation level of the innermost block.
+    pub fn current(&self) -> usize {
+        *self.levels.last().expect("indent stack is never empty")
+    }
+
+    /// The indentation levels of all enclosing blocks, outermost first.
+    pub fn levels(&self) -> &[usize] {
+        &self.levels
+    }
+
+    /// Whether line breaks are currently insignificant.
+    ///
+    /// See [`join_lines`].
+    pub fn is_joining(&self) -> bool {
+        self.joining > 0
+    }
+}
+
+impl Default for IndentStack {
+    fn default() -> Self {
+        Self {
+            levels: vec![0],
+            joining: 0,
+        }
+    }
+}
+
+impl State for IndentStack {
+    // Blocks are pushed and popped in nested order, so levels are never
+    // popped from under a checkpoint, and it only needs to remember how many
+    // there were.
+    type Checkpoint = (usize, usize);
+
+    fn checkpoint(&self) -> (usize, usize) {
+        (self.levels.len(), self.joining)
+    }
+
+    fn rewind(&mut self, (depth, joining): (usize, usize)) {
+        debug_assert!(self.levels.len() >= depth, "indent level popped early");
+        self.levels.truncate(depth);
+        self.joining = joining;
+    }
+}
+
+/// Match the start of a line, without consuming anything.
+pub fn line_start<In: Slice<Token = char>, Error>() -> impl Parser<In, (), Error> {
+    func(|context: &mut Context<In, Error>| {
+        if is_line_start(context) {
+            Ok(())
+        } else {
+            Err(None)
+        }
+    })
+}
+
+/// Skip whitespace that isn't significant.
+///
+/// Spaces and tabs are always skipped, but line breaks are only skipped
+/// inside [`join_lines`].
+pub fn space<In: Slice<Token = char>, Error>() -> impl Parser<In, (), Error> {
+    func(|context: &mut Context<In, Error>| {
+        let joining = context
+            .state::<IndentStack>()
+            .is_some_and(IndentStack::is_joining);
+
+        let len = context
+            .slice_current()
+            .prefix_len(|c| matches!(c, ' ' | '\t') || (joining && matches!(c, '\n' | '\r')));
+        context.set_location(context.location() + len);
+
+        Ok(())
+    })
+}
+
+/// Run a parser with line breaks made insignificant, as they are inside
+/// brackets in Python.
+pub fn join_lines<In, Out, Error>(
+    parser: impl Parser<In, Out, Error>,
+) -> impl Parser<In, Out, Error>
+where
+    In: Slice<Token = char>,
+{
+    func(move |context: &mut Context<In, Error>| {
+        stack(context).joining += 1;
+        let result = parser.parse(context);
+        stack(context).joining -= 1;
+        result
+    })
+}
+
+/// Match the indentation of a line in the current block.
+///
+/// Skips to the start of the next non-blank line first, if not already at
+/// the start of a line.
+pub fn same_indent<In, Error>() -> impl Parser<In, (), Error>
+where
+    In: Slice<Token = char>,
+    Error: From<ParseError>,
+{
+    func(|context: &mut Context<In, Error>| {
+        let start = context.location();
+        let level = indentation(context).ok_or(None)?;
+        let stack = stack(context);
+
+        if level == stack.current() {
+            Ok(())
+        } else if level < stack.current() && !stack.levels.contains(&level) {
+            Err(Some(inconsistent_dedent(context, start).into()))
+        } else {
+            Err(None)
+        }
+    })
+}
+
+/// Match a block of lines indented further than the current block, each
+/// matching `item`.
+///
+/// The block starts at the next non-blank line, so it can directly follow
+/// the line that opens it. It ends at the first line indented less than its
+/// first line. Indenting further than the first line, or dedenting to a
+/// level that doesn't match an enclosing block, is an error.
+///
+/// Line breaks are significant inside the block, even inside [`join_lines`].
+///
+/// Like other errors, these are discarded if a [`choice`] backtracks out of
+/// the block, unless it's wrapped in [`Parser::cut`].
+pub fn indented_block<In, Out, Error>(
+    item: impl Parser<In, Out, Error>,
+) -> impl Parser<In, Vec<Out>, Error>
+where
+    In: Slice<Token = char>,
+    Error: From<ParseError>,
+{
+    func(move |context: &mut Context<In, Error>| {
+        let level = indentation(context).ok_or(None)?;
+        if context.slice_current().is_empty() || level <= current_level(context) {
+            return Err(None);
+        }
+
+        let stack = stack(context);
+        let joining = std::mem::take(&mut stack.joining);
+        stack.levels.push(level);
+
+        let result = block_items(context, &item, level);
+
+        let stack = self::stack(context);
+        stack.levels.pop();
+        stack.joining = joining;
+
+        result
+    })
+}
+
+fn block_items<In, Out, Error>(
+    context: &mut Context<In, Error>,
+    item: &impl Parser<In, Out, Error>,
+    level: usize,
+) -> ParseResult<Vec<Out>, Error>
+where
+    In: Slice<Token = char>,
+    Error: From<ParseError>,
+{
+    let mut items = vec![item.parse(context)?];
+
+    loop {
+        let checkpoint = context.checkpoint();
+        let start = context.location();
+
+        let found = match indentation(context) {
+            Some(found) if !context.slice_current().is_empty() => found,
+            _ => {
+                context.rewind(checkpoint);
+                return Ok(items);
+            }
+        };
+
+        if found > level {
+            let span = context.span_from(start);
+            return Err(Some(
+                ParseError::new(ErrorKind::UnexpectedIndent, span).into(),
+            ));
+        }
+
+        if found < level {
+            let levels = &stack(context).levels;
+            let is_outer_level = levels[..levels.len() - 1].contains(&found);
+            if !is_outer_level {
+                return Err(Some(inconsistent_dedent(context, start).into()));
+            }
+
+            context.rewind(checkpoint);
+            return Ok(items);
+        }
+
+        match item.parse(context) {
+            Ok(output) => items.push(output),
+            Err(None) => {
+                context.rewind(checkpoint);
+                return Ok(items);
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Skip to the start of the next non-blank line (unless already at the start
+/// of a line), and then skip its indentation, outputting its width.
+///
+/// Fails if not at the start of a line, and there are no line breaks before
+/// the next non-whitespace token.
+fn indentation<In, Error>(context: &mut Context<In, Error>) -> Option<usize>
+where
+    In: Slice<Token = char>,
+{
+    let mut at_line_start = is_line_start(context);
+
+    loop {
+        let width = context
+            .slice_current()
+            .prefix_len(|c| matches!(c, ' ' | '\t'));
+        context.set_location(context.location() + width);
+
+        let line_break = context.slice_current().prefix_len(|c| c == '\r');
+        match context.slice_current().slice(line_break, line_break + 1) {
+            Some(rest) if rest.next().is_some_and(|(c, _)| c == '\n') => {
+                context.set_location(context.location() + line_break + 1);
+                at_line_start = true;
+            }
+            _ => return at_line_start.then_some(width),
+        }
+    }
+}
+
+fn is_line_start<In, Error>(context: &Context<In, Error>) -> bool
+where
+    In: Slice<Token = char>,
+{
+    let loc = context.location();
+    loc == 0
+        || context
+            .slice_all()
+            .slice(loc - 1, loc)
+            .and_then(|prev| prev.next())
+            .is_some_and(|(c, _)| c == '\n')
+}
+
+fn current_level<In: Slice, Error>(context: &Context<In, Error>) -> usize {
+    context
+        .state::<IndentStack>()
+        .map_or(0, IndentStack::current)
+}
+
+fn stack<In: Slice, Error>(context: &mut Context<In, Error>) -> &mut IndentStack {
+    if context.state::<IndentStack>().is_none() {
+        context.insert_state(IndentStack::default());
+    }
+    context.state_mut().expect("indent stack was just inserted")
+}
+
+fn inconsistent_dedent<In: Slice, Error>(context: &Context<In, Error>, start: usize) -> ParseError {
+    ParseError::new(ErrorKind::InconsistentDedent, context.span_from(start))
+}
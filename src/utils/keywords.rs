@@ -0,0 +1,216 @@
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+use derive_where::derive_where;
+
+use super::ident::Ident;
+use crate::context::slice::Slice;
+use crate::context::Context;
+use crate::error::{ErrorKind, ParseError};
+use crate::prelude::*;
+
+/// A table of keywords, each mapped to a value.
+///
+/// Keywords are stored in a trie, so an identifier can be looked up while it
+/// is being matched. See [`Ident::keyword`] and [`Ident::excluding`].
+#[derive(Debug, Clone)]
+pub struct Keywords<T> {
+    nodes: Vec<Node<T>>,
+}
+
+#[derive(Debug, Clone)]
+struct Node<T> {
+    /// Sorted by character.
+    children: Vec<(char, usize)>,
+    value: Option<T>,
+}
+
+impl<T> Node<T> {
+    fn new() -> Self {
+        Self {
+            children: vec![],
+            value: None,
+        }
+    }
+}
+
+impl<T> Keywords<T> {
+    pub fn new(keywords: impl IntoIterator<Item = (&'static str, T)>) -> Self {
+        let mut this = Self {
+            nodes: vec![Node::new()],
+        };
+        for (keyword, value) in keywords {
+            this.insert(keyword, value);
+        }
+        this
+    }
+
+    /// Add a keyword, replacing its previous value if it was already in the
+    /// table.
+    pub fn insert(&mut self, keyword: &str, value: T) {
+        let mut node = 0;
+        for c in keyword.chars() {
+            node = match self.child(node, c) {
+                Ok(child) => child,
+                Err(index) => {
+                    let child = self.nodes.len();
+                    self.nodes.push(Node::new());
+                    self.nodes[node].children.insert(index, (c, child));
+                    child
+                }
+            };
+        }
+        self.nodes[node].value = Some(value);
+    }
+
+    /// Look up the value of a keyword.
+    pub fn get(&self, keyword: &str) -> Option<&T> {
+        let mut cursor = Some(0);
+        for c in keyword.chars() {
+            cursor = self.step(cursor, c);
+        }
+        self.value(cursor)
+    }
+
+    /// Follow the edge from the node at `cursor` for `c`.
+    fn step(&self, cursor: Option<usize>, c: char) -> Option<usize> {
+        self.child(cursor?, c).ok()
+    }
+
+    fn value(&self, cursor: Option<usize>) -> Option<&T> {
+        self.nodes[cursor?].value.as_ref()
+    }
+
+    fn child(&self, node: usize, c: char) -> Result<usize, usize> {
+        let children = &self.nodes[node].children;
+        children
+            .binary_search_by_key(&c, |&(c, _)| c)
+            .map(|index| children[index].1)
+    }
+}
+
+impl<T> FromIterator<(&'static str, T)> for Keywords<T> {
+    fn from_iter<I: IntoIterator<Item = (&'static str, T)>>(iter: I) -> Self {
+        Self::new(iter)
+    }
+}
+
+impl<In: Slice<Token = char>, Error> Ident<In, Error> {
+    /// Match an identifier that is a keyword, outputting the keyword's value.
+    ///
+    /// The keyword is looked up while the identifier is matched, so this is
+    /// faster than matching each keyword in turn.
+    ///
+    /// The table is shared by the parser and its clones. Pass an
+    /// `Rc<Keywords<T>>` to share it with other parsers too.
+    pub fn keyword<T: Clone>(self, keywords: impl Into<Rc<Keywords<T>>>) -> Keyword<In, Error, T> {
+        Keyword {
+            ident: self,
+            keywords: keywords.into(),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Match an identifier that isn't a keyword.
+    ///
+    /// A keyword doesn't match, so that a keyword can be tried instead. Use
+    /// [`Excluding::or_error`] to report it as an error.
+    ///
+    /// The table is shared like with [`Ident::keyword`].
+    pub fn excluding<T>(self, keywords: impl Into<Rc<Keywords<T>>>) -> Excluding<In, Error, T> {
+        Excluding {
+            ident: self,
+            keywords: keywords.into(),
+            make_error: None,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+#[derive_where(Clone)]
+#[derive_where(Debug; T)]
+pub struct Keyword<In: Slice<Token = char>, Error, T> {
+    ident: Ident<In, Error>,
+    keywords: Rc<Keywords<T>>,
+    _phantom: PhantomData<*const (In, Error)>,
+}
+
+impl<In, Error, T> Parser<In, T, Error> for Keyword<In, Error, T>
+where
+    In: Slice<Token = char>,
+    T: Clone,
+{
+    fn parse(&self, context: &mut Context<In, Error>) -> ParseResult<T, Error> {
+        context.step()?;
+        let (len, value) =
+            scan(&self.ident, &self.keywords, context.slice_current()).ok_or(None)?;
+        let value = value.ok_or(None)?.clone();
+
+        context.set_location(context.location() + len);
+        Ok(value)
+    }
+}
+
+#[derive_where(Clone)]
+#[derive_where(Debug; T)]
+pub struct Excluding<In: Slice<Token = char>, Error, T> {
+    ident: Ident<In, Error>,
+    keywords: Rc<Keywords<T>>,
+    make_error: Option<fn(ParseError) -> Error>,
+    _phantom: PhantomData<*const (In, Error)>,
+}
+
+impl<In: Slice<Token = char>, Error, T> Excluding<In, Error, T> {
+    /// Report a keyword as an error, rather than failing to match.
+    pub fn or_error(mut self) -> Self
+    where
+        Error: From<ParseError>,
+    {
+        self.make_error = Some(Error::from);
+        self
+    }
+}
+
+impl<In, Error, T> Parser<In, In, Error> for Excluding<In, Error, T>
+where
+    In: Slice<Token = char>,
+{
+    fn parse(&self, context: &mut Context<In, Error>) -> ParseResult<In, Error> {
+        context.step()?;
+        let start = context.location();
+        let current = context.slice_current();
+        let (len, value) = scan(&self.ident, &self.keywords, current).ok_or(None)?;
+        let ident = current.slice(0, len).expect("couldn't get slice");
+
+        if value.is_some() {
+            let make_error = self.make_error.ok_or(None)?;
+            let mut keyword = String::new();
+            let mut rest = ident;
+            while let Some((c, next)) = rest.next() {
+                keyword.push(c);
+                rest = next;
+            }
+
+            let span = context.span(start, start + len);
+            let kind = ErrorKind::KeywordAsIdent { keyword };
+            return Err(Some(make_error(ParseError::new(kind, span))));
+        }
+
+        context.set_location(start + len);
+        Ok(ident)
+    }
+}
+
+/// Match an identifier, looking it up in the keyword table at the same time.
+fn scan<'k, In, Error, T>(
+    ident: &Ident<In, Error>,
+    keywords: &'k Keywords<T>,
+    input: In,
+) -> Option<(usize, Option<&'k T>)>
+where
+    In: Slice<Token = char>,
+{
+    let mut cursor = Some(0);
+    let len = ident.scan(input, |c| cursor = keywords.step(cursor, c))?;
+    Some((len, keywords.value(cursor)))
+}
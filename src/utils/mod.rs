@@ -0,0 +1,36 @@
+pub mod binary;
+mod ident;
+pub mod indent;
+mod keywords;
+mod number;
+mod string;
+mod trivia;
+
+pub use self::ident::{ident, Ident};
+pub use self::keywords::{Excluding, Keyword, Keywords};
+pub use self::number::{float, int, number, uint, Integer, Number};
+pub use self::string::{string_literal, StringConfig};
+pub use self::trivia::{trivia, Comment, CommentKind, TriviaConfig};
+
+use crate::context::slice::Slice;
+use crate::prelude::*;
+
+pub fn space<In: Slice<Token = char>, Error>() -> impl Parser<In, (), Error> {
+    take_while(|c: char| c.is_ascii_whitespace()).drop()
+}
+
+pub fn kwd<'a, Error>(s: &'static str) -> impl Parser<&'a str, &'a str, Error> {
+    ident().filter(move |&ident| ident == s)
+}
+
+/// Like [`kwd`], but ignoring case, outputting the keyword as it appears in
+/// the input.
+pub fn kwd_ignore_case<'a, Error>(
+    s: &'static str,
+    fold: CaseFold,
+) -> impl Parser<&'a str, &'a str, Error> {
+    ident().filter(move |&ident: &&str| {
+        fold.strip_literal(ident, s)
+            .is_some_and(|rest| rest.is_empty())
+    })
+}
@@ -0,0 +1,238 @@
+use std::num::ParseIntError;
+use std::str::FromStr;
+
+use crate::context::slice::Slice;
+use crate::context::Context;
+use crate::error::{ErrorKind, ParseError};
+use crate::prelude::*;
+
+/// A primitive integer type, which can be parsed by [`int`] and [`uint`].
+pub trait Integer: Sized {
+    /// Whether the type can represent negative numbers.
+    const SIGNED: bool;
+
+    fn from_str_radix(s: &str, radix: u32) -> Result<Self, ParseIntError>;
+}
+
+macro_rules! impl_integer {
+    ($($ty:ty: $signed:literal),*) => {
+        $(
+            impl Integer for $ty {
+                const SIGNED: bool = $signed;
+
+                fn from_str_radix(s: &str, radix: u32) -> Result<Self, ParseIntError> {
+                    <$ty>::from_str_radix(s, radix)
+                }
+            }
+        )*
+    };
+}
+
+impl_integer! {
+    u8: false, u16: false, u32: false, u64: false, u128: false, usize: false,
+    i8: true, i16: true, i32: true, i64: true, i128: true, isize: true
+}
+
+/// The output of [`number`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Number<I, F> {
+    Int(I),
+    Float(F),
+}
+
+/// Parse an integer in the given radix, with an optional sign.
+///
+/// Digits may be separated by underscores, as in `1_000`. A `-` sign is only
+/// accepted if the integer type is signed.
+///
+/// Reports an error if the integer doesn't fit in the output type.
+///
+/// Panics if `radix` isn't between 2 and 36.
+pub fn int<In, Error, T>(radix: u32) -> impl Parser<In, T, Error>
+where
+    In: Slice<Token = char>,
+    Error: From<ParseError>,
+    T: Integer,
+{
+    check_radix(radix);
+    func(move |context: &mut Context<In, Error>| {
+        let start = context.location();
+        let literal = chain((sign(T::SIGNED), digits(radix)))
+            .to_slice()
+            .parse(context)?;
+        parse_int(context, start, literal, radix)
+    })
+}
+
+/// Parse an integer in the given radix, without a sign.
+///
+/// See [`int`] for more information.
+///
+/// Panics if `radix` isn't between 2 and 36.
+pub fn uint<In, Error, T>(radix: u32) -> impl Parser<In, T, Error>
+where
+    In: Slice<Token = char>,
+    Error: From<ParseError>,
+    T: Integer,
+{
+    check_radix(radix);
+    func(move |context: &mut Context<In, Error>| {
+        let start = context.location();
+        let literal = digits(radix).parse(context)?;
+        parse_int(context, start, literal, radix)
+    })
+}
+
+/// Parse a decimal floating point number, with an optional sign, fractional
+/// part and exponent, as in `-1_000.5e-3`.
+///
+/// The number is parsed exactly as [`str::parse`] would parse it (after
+/// removing digit separators).
+pub fn float<In, Error, F>() -> impl Parser<In, F, Error>
+where
+    In: Slice<Token = char>,
+    F: FromStr,
+{
+    func(|context: &mut Context<In, Error>| {
+        let (literal, _) = decimal().parse(context)?;
+        strip_separators(literal).parse().map_err(|_| None)
+    })
+}
+
+/// Parse an integer or a floating point number.
+///
+/// Integers may have a `0x`, `0o` or `0b` prefix (after the sign) to change
+/// their radix. Decimal numbers with a fractional part or exponent are
+/// parsed as floats, as in [`float`].
+///
+/// Reports an error if an integer doesn't fit in the output type.
+pub fn number<In, Error, I, F>() -> impl Parser<In, Number<I, F>, Error>
+where
+    In: Slice<Token = char>,
+    Error: From<ParseError>,
+    I: Integer,
+    F: FromStr,
+{
+    func(|context: &mut Context<In, Error>| {
+        let start = context.location();
+        let prefixed = chain((
+            sign(I::SIGNED).to_slice(),
+            choice((
+                chain((just('0'), just('x'), digits(16))).map(|(_, _, d)| (16, d)),
+                chain((just('0'), just('o'), digits(8))).map(|(_, _, d)| (8, d)),
+                chain((just('0'), just('b'), digits(2))).map(|(_, _, d)| (2, d)),
+            )),
+        ));
+
+        if let Ok((sign, (radix, digits))) = prefixed.parse(context) {
+            let literal = strip_separators(sign) + &strip_separators(digits);
+            return match I::from_str_radix(&literal, radix) {
+                Ok(value) => Ok(Number::Int(value)),
+                Err(_) => Err(Some(out_of_range(context, start).into())),
+            };
+        }
+
+        let (literal, is_float) = decimal().parse(context)?;
+        if is_float {
+            let value = strip_separators(literal).parse().map_err(|_| None)?;
+            Ok(Number::Float(value))
+        } else {
+            parse_int(context, start, literal, 10).map(Number::Int)
+        }
+    })
+}
+
+/// Match a decimal number, outputting whether it has a fractional part or
+/// exponent.
+fn decimal<In, Error>() -> impl Parser<In, (In, bool), Error>
+where
+    In: Slice<Token = char>,
+{
+    let fraction = chain((just('.'), digits(10)));
+    let exponent = chain((
+        pred(|c| c == 'e' || c == 'E'),
+        choice((just('-').drop(), just('+').drop(), nothing())),
+        digits(10),
+    ));
+
+    chain((
+        sign(true),
+        digits(10),
+        optional(fraction),
+        optional(exponent),
+    ))
+    .map_with(|(_, _, fraction, exponent), extra| (extra.slice(), fraction || exponent))
+}
+
+/// Match an optional sign.
+fn sign<In, Error>(allow_minus: bool) -> impl Parser<In, (), Error>
+where
+    In: Slice<Token = char>,
+{
+    choice((
+        pred(move |c| c == '+' || (allow_minus && c == '-')).drop(),
+        nothing(),
+    ))
+}
+
+/// Check a radix up front, rather than letting [`char::is_digit`] panic in
+/// the middle of a parse.
+fn check_radix(radix: u32) {
+    assert!(
+        (2..=36).contains(&radix),
+        "radix must be between 2 and 36, got {radix}"
+    );
+}
+
+/// Match digits in the given radix, which may be separated by underscores.
+fn digits<In, Error>(radix: u32) -> impl Parser<In, In, Error>
+where
+    In: Slice<Token = char>,
+{
+    check_radix(radix);
+    chain((
+        pred(move |c: char| c.is_digit(radix)),
+        take_while(move |c: char| c.is_digit(radix) || c == '_'),
+    ))
+    .to_slice()
+}
+
+/// Match a parser optionally, outputting whether it matched.
+fn optional<In, Out, Error>(parser: impl Parser<In, Out, Error>) -> impl Parser<In, bool, Error>
+where
+    In: Slice,
+{
+    choice((parser.map(|_| true), nothing().map(|_| false)))
+}
+
+fn parse_int<In, Error, T>(
+    context: &Context<In, Error>,
+    start: usize,
+    literal: In,
+    radix: u32,
+) -> ParseResult<T, Error>
+where
+    In: Slice<Token = char>,
+    Error: From<ParseError>,
+    T: Integer,
+{
+    // The literal has already been validated, so this can only fail if it's
+    // out of range.
+    T::from_str_radix(&strip_separators(literal), radix)
+        .map_err(|_| Some(out_of_range(context, start).into()))
+}
+
+fn strip_separators<In: Slice<Token = char>>(mut literal: In) -> String {
+    let mut s = String::with_capacity(literal.len());
+    while let Some((c, rest)) = literal.next() {
+        if c != '_' {
+            s.push(c);
+        }
+        literal = rest;
+    }
+    s
+}
+
+fn out_of_range<In: Slice, Error>(context: &Context<In, Error>, start: usize) -> ParseError {
+    ParseError::new(ErrorKind::IntegerOutOfRange, context.span_from(start))
+}
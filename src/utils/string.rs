@@ -0,0 +1,323 @@
+use std::borrow::Cow;
+
+use crate::context::span::Span;
+use crate::context::Context;
+use crate::error::{ErrorKind, ParseError};
+use crate::prelude::*;
+
+/// The syntax of a string literal, for [`string_literal`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StringConfig {
+    quote: char,
+    escapes: Vec<(char, char)>,
+    json_unicode: bool,
+    rust_unicode: bool,
+    raw: bool,
+    allow_control: bool,
+}
+
+impl StringConfig {
+    /// Strings delimited by `quote`, with the escapes `\n`, `\r`, `\t`, `\\`
+    /// and `\` followed by the quote.
+    pub fn new(quote: char) -> Self {
+        Self {
+            quote,
+            escapes: vec![
+                ('n', '\n'),
+                ('r', '\r'),
+                ('t', '\t'),
+                ('\\', '\\'),
+                (quote, quote),
+            ],
+            json_unicode: false,
+            rust_unicode: false,
+            raw: false,
+            allow_control: false,
+        }
+    }
+
+    /// JSON strings.
+    pub fn json() -> Self {
+        Self::new('"')
+            .escape('/', '/')
+            .escape('b', '\u{8}')
+            .escape('f', '\u{c}')
+            .json_unicode(true)
+    }
+
+    /// Rust strings, including raw strings.
+    pub fn rust() -> Self {
+        Self::new('"')
+            .escape('\'', '\'')
+            .escape('0', '\0')
+            .rust_unicode(true)
+            .raw(true)
+            .allow_control(true)
+    }
+
+    /// Add an escape, where `\` followed by `c` stands for `replacement`.
+    pub fn escape(mut self, c: char, replacement: char) -> Self {
+        self.escapes.push((c, replacement));
+        self
+    }
+
+    /// Allow `\uXXXX` escapes, where surrogate pairs are written as two
+    /// escapes, as in JSON.
+    pub fn json_unicode(mut self, allow: bool) -> Self {
+        self.json_unicode = allow;
+        self
+    }
+
+    /// Allow `\u{X}` escapes of up to six hex digits, as in Rust.
+    pub fn rust_unicode(mut self, allow: bool) -> Self {
+        self.rust_unicode = allow;
+        self
+    }
+
+    /// Allow raw strings without escapes, such as `r"..."` and `r#"..."#`.
+    pub fn raw(mut self, allow: bool) -> Self {
+        self.raw = allow;
+        self
+    }
+
+    /// Allow unescaped control characters (U+0000 to U+001F), such as line
+    /// breaks.
+    pub fn allow_control(mut self, allow: bool) -> Self {
+        self.allow_control = allow;
+        self
+    }
+}
+
+impl Default for StringConfig {
+    fn default() -> Self {
+        Self::new('"')
+    }
+}
+
+/// Parse a string literal, outputting its contents with escapes replaced.
+///
+/// The output borrows from the input if there are no escapes.
+///
+/// Invalid escapes and unescaped control characters are reported as errors,
+/// but don't stop the string from being parsed. Invalid escapes are left out
+/// of the output. An unterminated string is an error.
+pub fn string_literal<'a, Error>(config: StringConfig) -> impl Parser<&'a str, Cow<'a, str>, Error>
+where
+    Error: From<ParseError>,
+{
+    func(move |context: &mut Context<&'a str, Error>| {
+        let start = context.location();
+        let input = context.slice_current();
+
+        let (fence, body_start) = opening_quote(&config, input).ok_or(None)?;
+        let mut scanner = Scanner {
+            config: &config,
+            context,
+            start,
+            input,
+            pos: body_start,
+            chunk_start: body_start,
+            owned: None,
+        };
+
+        let body_end = match fence {
+            Some(fence) => scanner.raw_body(fence),
+            None => scanner.body(),
+        };
+
+        let Some(body_end) = body_end else {
+            let span = Span::new(start, start + body_start);
+            return Err(Some(
+                ParseError::new(ErrorKind::UnterminatedString, span).into(),
+            ));
+        };
+
+        let end = start + scanner.pos;
+        let output = match scanner.owned {
+            Some(mut owned) => {
+                owned.push_str(&input[scanner.chunk_start..body_end]);
+                Cow::Owned(owned)
+            }
+            None => Cow::Borrowed(&input[body_start..body_end]),
+        };
+
+        context.set_location(end);
+        Ok(output)
+    })
+}
+
+/// Match the opening quote, outputting the number of `#`s for a raw string,
+/// and the offset of the body.
+fn opening_quote(config: &StringConfig, input: &str) -> Option<(Option<usize>, usize)> {
+    if config.raw {
+        if let Some(rest) = input.strip_prefix('r') {
+            let fence = rest.len() - rest.trim_start_matches('#').len();
+            let rest = &rest[fence..];
+            if rest.starts_with(config.quote) {
+                return Some((
+                    Some(fence),
+                    input.len() - rest.len() + config.quote.len_utf8(),
+                ));
+            }
+        }
+    }
+
+    input
+        .starts_with(config.quote)
+        .then_some((None, config.quote.len_utf8()))
+}
+
+/// Whether a character must be escaped, unless
+/// [`StringConfig::allow_control`] is set.
+///
+/// This matches JSON, which only forbids the C0 controls, so U+007F and the
+/// C1 controls are allowed.
+fn is_control(c: char) -> bool {
+    c < ' '
+}
+
+struct Scanner<'s, 'a, Error> {
+    config: &'s StringConfig,
+    context: &'s mut Context<&'a str, Error>,
+    start: usize,
+
+    input: &'a str,
+    pos: usize,
+
+    /// The start of the input that hasn't been copied to `owned` yet.
+    chunk_start: usize,
+    owned: Option<String>,
+}
+
+impl<Error: From<ParseError>> Scanner<'_, '_, Error> {
+    /// Scan the body of a string, outputting where it ends.
+    fn body(&mut self) -> Option<usize> {
+        loop {
+            let c = self.peek()?;
+            let c_start = self.pos;
+            self.pos += c.len_utf8();
+
+            if c == self.config.quote {
+                return Some(c_start);
+            } else if c == '\\' {
+                let owned = self.owned.get_or_insert_with(String::new);
+                owned.push_str(&self.input[self.chunk_start..c_start]);
+                self.escape(c_start)?;
+                self.chunk_start = self.pos;
+            } else if is_control(c) && !self.config.allow_control {
+                self.report(ErrorKind::ControlCharacter, c_start);
+            }
+        }
+    }
+
+    /// Scan the body of a raw string, outputting where it ends.
+    fn raw_body(&mut self, fence: usize) -> Option<usize> {
+        loop {
+            let c = self.peek()?;
+            let c_start = self.pos;
+            self.pos += c.len_utf8();
+
+            if c == self.config.quote {
+                let rest = &self.input[self.pos..];
+                if rest.len() >= fence && rest.bytes().take(fence).all(|b| b == b'#') {
+                    self.pos += fence;
+                    return Some(c_start);
+                }
+            } else if is_control(c) && !self.config.allow_control {
+                self.report(ErrorKind::ControlCharacter, c_start);
+            }
+        }
+    }
+
+    /// Scan an escape (after the backslash), adding it to the output.
+    fn escape(&mut self, escape_start: usize) -> Option<()> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+
+        let replacement = self
+            .config
+            .escapes
+            .iter()
+            .find(|&&(escape, _)| escape == c)
+            .map(|&(_, replacement)| replacement);
+
+        let escaped = match replacement {
+            Some(replacement) => Some(replacement),
+            None if c == 'u' && self.config.rust_unicode && self.peek() == Some('{') => {
+                self.rust_unicode()
+            }
+            None if c == 'u' && self.config.json_unicode => self.json_unicode(),
+            None => None,
+        };
+
+        match escaped {
+            Some(escaped) => self
+                .owned
+                .as_mut()
+                .expect("escape outside string")
+                .push(escaped),
+            None => self.report(ErrorKind::InvalidEscape, escape_start),
+        }
+
+        Some(())
+    }
+
+    /// Scan the `{X}` of a Rust `\u{X}` escape.
+    ///
+    /// An invalid escape is skipped up to its closing brace, if it has one,
+    /// so that none of it ends up in the output.
+    fn rust_unicode(&mut self) -> Option<char> {
+        let rest = &self.input[self.pos + 1..];
+        let len = rest
+            .find(|c: char| !c.is_ascii_alphanumeric())
+            .unwrap_or(rest.len());
+        let closed = rest[len..].starts_with('}');
+        self.pos += 1 + len + usize::from(closed);
+        if !closed || !(1..=6).contains(&len) {
+            return None;
+        }
+
+        u32::from_str_radix(&rest[..len], 16)
+            .ok()
+            .and_then(char::from_u32)
+    }
+
+    /// Scan the `XXXX` of a JSON `\uXXXX` escape, and its pair if it's a
+    /// surrogate.
+    fn json_unicode(&mut self) -> Option<char> {
+        let high = self.hex4()?;
+        if !(0xd800..0xdc00).contains(&high) {
+            return char::from_u32(high);
+        }
+
+        if !self.input[self.pos..].starts_with("\\u") {
+            return None;
+        }
+        self.pos += 2;
+        let low = self.hex4()?;
+        if !(0xdc00..0xe000).contains(&low) {
+            return None;
+        }
+
+        char::from_u32(0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00))
+    }
+
+    fn hex4(&mut self) -> Option<u32> {
+        let digits = self.input.get(self.pos..self.pos + 4)?;
+        if !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+
+        self.pos += 4;
+        u32::from_str_radix(digits, 16).ok()
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn report(&mut self, kind: ErrorKind, from: usize) {
+        let span = Span::new(self.start + from, self.start + self.pos);
+        self.context.report(ParseError::new(kind, span).into());
+    }
+}
@@ -0,0 +1,186 @@
+use crate::context::slice::Slice;
+use crate::context::span::Span;
+use crate::context::Context;
+use crate::error::{ErrorKind, ParseError};
+use crate::prelude::*;
+use crate::primitive::strip_literal;
+
+/// The syntax of comments, for [`trivia`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TriviaConfig {
+    line: Vec<&'static str>,
+    block: Vec<(&'static str, &'static str)>,
+    nested: bool,
+    collect: bool,
+}
+
+impl TriviaConfig {
+    /// Whitespace only, without any comments.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `//` line comments and `/* */` block comments.
+    pub fn c() -> Self {
+        Self::new().line_comment("//").block_comment("/*", "*/")
+    }
+
+    /// Add a line comment, starting with `start` and ending at the end of the
+    /// line.
+    pub fn line_comment(mut self, start: &'static str) -> Self {
+        self.line.push(start);
+        self
+    }
+
+    /// Add a block comment, delimited by `open` and `close`.
+    pub fn block_comment(mut self, open: &'static str, close: &'static str) -> Self {
+        self.block.push((open, close));
+        self
+    }
+
+    /// Allow block comments to be nested.
+    pub fn nested(mut self, nested: bool) -> Self {
+        self.nested = nested;
+        self
+    }
+
+    /// Collect the comments that are skipped, rather than outputting an
+    /// empty list.
+    pub fn collect(mut self, collect: bool) -> Self {
+        self.collect = collect;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CommentKind {
+    Line,
+    Block,
+}
+
+/// A comment skipped by [`trivia`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Comment<In> {
+    kind: CommentKind,
+    span: Span,
+    text: In,
+}
+
+impl<In: Slice> Comment<In> {
+    pub fn kind(&self) -> CommentKind {
+        self.kind
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// The text of the comment, including its delimiters.
+    pub fn text(&self) -> In {
+        self.text
+    }
+}
+
+/// Skip whitespace and comments.
+///
+/// Outputs the comments that were skipped, if enabled with
+/// [`TriviaConfig::collect`].
+///
+/// An unterminated block comment is reported as an error at its opening
+/// delimiter.
+pub fn trivia<In, Error>(config: TriviaConfig) -> impl Parser<In, Vec<Comment<In>>, Error>
+where
+    In: Slice<Token = char>,
+    Error: From<ParseError>,
+{
+    func(move |context: &mut Context<In, Error>| {
+        let mut comments = vec![];
+
+        loop {
+            let whitespace = context.slice_current().prefix_len(char::is_whitespace);
+            context.set_location(context.location() + whitespace);
+
+            let start = context.location();
+            let kind = if line_comment(&config, context) {
+                CommentKind::Line
+            } else if block_comment(&config, context)? {
+                CommentKind::Block
+            } else {
+                return Ok(comments);
+            };
+
+            if config.collect {
+                let text = context
+                    .slice_all()
+                    .slice(start, context.location())
+                    .expect("couldn't get slice");
+                comments.push(Comment {
+                    kind,
+                    span: context.span_from(start),
+                    text,
+                });
+            }
+        }
+    })
+}
+
+/// Skip a line comment, if there is one.
+fn line_comment<In, Error>(config: &TriviaConfig, context: &mut Context<In, Error>) -> bool
+where
+    In: Slice<Token = char>,
+{
+    let current = context.slice_current();
+    let Some(rest) = config
+        .line
+        .iter()
+        .find_map(|start| strip_literal(current, start))
+    else {
+        return false;
+    };
+
+    let len = current.len() - rest.len() + rest.prefix_len(|c| c != '\n');
+    context.set_location(context.location() + len);
+    true
+}
+
+/// Skip a block comment, if there is one.
+fn block_comment<In, Error>(
+    config: &TriviaConfig,
+    context: &mut Context<In, Error>,
+) -> ParseResult<bool, Error>
+where
+    In: Slice<Token = char>,
+    Error: From<ParseError>,
+{
+    let start = context.location();
+    let current = context.slice_current();
+    let Some((open, close, mut rest)) = config
+        .block
+        .iter()
+        .find_map(|&(open, close)| Some((open, close, strip_literal(current, open)?)))
+    else {
+        return Ok(false);
+    };
+    let open_end = start + (current.len() - rest.len());
+
+    let mut depth = 1;
+    while depth > 0 {
+        if let Some(after) = strip_literal(rest, close) {
+            depth -= 1;
+            rest = after;
+        } else if let Some(after) = strip_literal(rest, open).filter(|_| config.nested) {
+            depth += 1;
+            rest = after;
+        } else if let Some((_, after)) = rest.next() {
+            rest = after;
+        } else {
+            let span = context.span(start, open_end);
+            return Err(Some(
+                ParseError::new(ErrorKind::UnterminatedComment, span).into(),
+            ));
+        }
+    }
+
+    context.set_location(start + (current.len() - rest.len()));
+    Ok(true)
+}